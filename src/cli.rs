@@ -0,0 +1,761 @@
+use std::{
+    fmt::Write,
+    fs::{read_to_string, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, FixedOffset, SecondsFormat, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::eyre::{anyhow, bail, Result, WrapErr};
+use serde::Serialize;
+
+use crate::{
+    handlers::{
+        config::{Alignment, CompleteConfig},
+        import::{import_csv, import_vcard, insert, Conflict, Summary},
+        storage::{Person, Storage},
+    },
+    utils::{
+        availability::{status_of, WorkingHours},
+        git::{parse_log, propose, read_log, Pick, Proposal},
+        pathing::config_path,
+        planner::{find_windows, plan_headers, plan_row, DEFAULT_DAYS, DEFAULT_MINUTES},
+        sorting::Sort,
+        text::{align_columns, align_text, table_row, TABLE_HEADERS},
+        timezones::{format_offset, parse_local_time, Place, Timezone},
+        vcard::to_vcards,
+    },
+};
+
+/// Converting/tracking timezones of others relative to your local time.
+/// Starts the interactive table when no command is given.
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about,
+    after_help = "Exit codes: 0 on success, 1 when a command fails, 2 when the arguments are wrong."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Lists everyone being tracked, with their offset and current time.
+    List {
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Starts tracking someone, or changes the timezone of someone already tracked.
+    Add {
+        name: String,
+        /// An offset such as +5 or UTC-03:30, or a zone such as Europe/Berlin.
+        #[arg(allow_hyphen_values = true)]
+        timezone: String,
+    },
+    /// Adds many people at once from lines of "name,timezone", such as a CSV file,
+    /// or from the contacts of a vCard file.
+    Import {
+        /// The file to read, or standard input when left out.
+        file: Option<PathBuf>,
+        /// Guessed from the extension of the file, otherwise CSV.
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+        /// What to do with names that are already being tracked.
+        #[arg(long, value_enum, default_value_t = OnConflict::Skip)]
+        on_conflict: OnConflict,
+    },
+    /// Proposes people and their offsets from the commit history of a git repository.
+    /// Proposals are printed so that they can be edited and imported, unless applied.
+    FromGit {
+        /// The repository to look through.
+        #[arg(default_value = ".")]
+        repository: PathBuf,
+        /// Only look at commits more recent than this, such as "6 months ago".
+        #[arg(long)]
+        since: Option<String>,
+        /// Which offset to propose for authors that have committed from several.
+        #[arg(long, value_enum, default_value_t = PickOffset::Common)]
+        pick: PickOffset,
+        /// Add the proposals right away.
+        #[arg(long)]
+        apply: bool,
+        /// What to do with names that are already being tracked, when applying.
+        #[arg(long, value_enum, default_value_t = OnConflict::Skip)]
+        on_conflict: OnConflict,
+    },
+    /// Shows or changes when someone works.
+    Hours {
+        name: String,
+        /// Days followed by a time range, such as "Sun-Thu 08:00-16:00".
+        hours: Option<String>,
+        /// Goes back to the working hours of the configuration.
+        #[arg(long, conflicts_with = "hours")]
+        default: bool,
+    },
+    /// Finds when everyone's working hours overlap, least inconvenient first.
+    /// Percentages show how far each window is from the middle of someone's working day.
+    Plan {
+        /// The people being tracked that are meeting.
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// How many days ahead to look, up to a year.
+        #[arg(long, default_value_t = DEFAULT_DAYS, value_parser = clap::value_parser!(i64).range(1..=366))]
+        days: i64,
+        /// Leaves out windows shorter than this many minutes, up to a day.
+        #[arg(long, default_value_t = DEFAULT_MINUTES, value_parser = clap::value_parser!(i64).range(1..=1440))]
+        minutes: i64,
+        /// How many windows to show at most.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Stops tracking someone.
+    Remove { name: String },
+    /// Shows the current time of someone being tracked, or of a timezone.
+    Now { name: String },
+    /// Converts a time such as 15:00, Fri 15:00, or 2023-10-20T15:00 from one place to another.
+    Convert {
+        time: String,
+        /// Someone being tracked, a timezone, or "local".
+        #[arg(long, allow_hyphen_values = true)]
+        from: String,
+        /// Someone being tracked, a timezone, or "local".
+        #[arg(long, allow_hyphen_values = true)]
+        to: String,
+    },
+}
+
+/// How the list of everyone being tracked is printed.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned columns, the same as the interface.
+    Table,
+    Json,
+    Csv,
+    /// Tab separated values.
+    Tsv,
+    /// A vCard for everyone, with their name and timezone.
+    Vcard,
+}
+
+/// What to do with names that are already being tracked, as given on the command line.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Keep whoever is already tracked.
+    Skip,
+    /// Give whoever is already tracked the new timezone.
+    Overwrite,
+    /// Add them anyway, numbering their name.
+    Rename,
+}
+
+impl From<OnConflict> for Conflict {
+    fn from(on_conflict: OnConflict) -> Self {
+        match on_conflict {
+            OnConflict::Skip => Self::Skip,
+            OnConflict::Overwrite => Self::Overwrite,
+            OnConflict::Rename => Self::Rename,
+        }
+    }
+}
+
+/// Which offset to propose for an author, as given on the command line.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickOffset {
+    /// The one most of their commits have.
+    Common,
+    /// The one their latest commit has.
+    Recent,
+}
+
+impl From<PickOffset> for Pick {
+    fn from(pick: PickOffset) -> Self {
+        match pick {
+            PickOffset::Common => Self::Common,
+            PickOffset::Recent => Self::Recent,
+        }
+    }
+}
+
+/// What kind of file is being imported.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    Vcard,
+}
+
+impl ImportFormat {
+    fn guess(path: Option<&Path>) -> Self {
+        let extension = path
+            .and_then(Path::extension)
+            .map(|e| e.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("vcf" | "vcard") => Self::Vcard,
+            _ => Self::Csv,
+        }
+    }
+}
+
+/// A row of the table, in a shape that other programs can read.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct Record {
+    user: String,
+    /// The offset or IANA name that was given when adding someone.
+    timezone: String,
+    /// The offset currently observed, such as `+05:30`.
+    offset: String,
+    offset_seconds: i64,
+    /// The current time as ISO-8601, including the offset.
+    time: String,
+    /// Either `working`, `off-hours`, or `sleeping`.
+    status: String,
+    /// Seconds until work starts or ends, left out for people that never work.
+    status_changes_in_seconds: Option<i64>,
+}
+
+impl Record {
+    fn new(person: &Person, instant: DateTime<Utc>, default_hours: &WorkingHours) -> Self {
+        let status = status_of(person, instant, default_hours);
+
+        let offset_seconds = person.timezone.offset_seconds_at(instant);
+
+        let time = FixedOffset::east_opt(offset_seconds as i32).map_or_else(
+            || person.timezone.local_time_at(instant).to_string(),
+            |offset| {
+                instant
+                    .with_timezone(&offset)
+                    .to_rfc3339_opts(SecondsFormat::Secs, false)
+            },
+        );
+
+        Self {
+            user: person.name.clone(),
+            timezone: person.timezone.to_string(),
+            offset: format_offset(offset_seconds),
+            offset_seconds,
+            time,
+            status: status.availability.to_string(),
+            status_changes_in_seconds: status.change_in.map(|d| d.num_seconds()),
+        }
+    }
+}
+
+/// Everyone being tracked, in the sort order of the configuration.
+fn sorted_people(
+    storage: &Storage,
+    config: &CompleteConfig,
+    instant: DateTime<Utc>,
+) -> Vec<Person> {
+    let mut people = storage.get_all();
+
+    Sort::new(config.frontend.sort_key, config.frontend.sort_direction).apply(&mut people, instant);
+
+    people
+}
+
+/// Rows of text lined up in columns under their headers.
+fn aligned(headers: Vec<String>, rows: Vec<Vec<String>>, alignment: &Alignment) -> String {
+    let (aligned_rows, maximums) =
+        align_columns(rows, headers.clone(), headers.len(), alignment.clone());
+
+    let headers = headers
+        .iter()
+        .zip(&maximums)
+        .map(|(header, maximum)| align_text(header, *maximum, alignment.clone()))
+        .collect::<Vec<String>>();
+
+    let mut output = String::new();
+
+    for cells in std::iter::once(headers).chain(aligned_rows) {
+        output.push_str(cells.join(" ").trim_end());
+        output.push('\n');
+    }
+
+    output
+}
+
+/// The same table as the interface shows, as plain text.
+fn table(people: &[Person], config: &CompleteConfig, instant: DateTime<Utc>) -> String {
+    let rows = people
+        .iter()
+        .map(|p| table_row(p, instant, &config.frontend))
+        .collect::<Vec<Vec<String>>>();
+
+    aligned(
+        TABLE_HEADERS.iter().map(ToString::to_string).collect(),
+        rows,
+        &config.frontend.alignment,
+    )
+}
+
+fn delimited(records: &[Record], delimiter: u8) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn list(
+    storage: &Storage,
+    config: &CompleteConfig,
+    instant: DateTime<Utc>,
+    format: Format,
+) -> Result<String> {
+    let people = sorted_people(storage, config, instant);
+
+    let records = people
+        .iter()
+        .map(|p| Record::new(p, instant, &config.frontend.working_hours))
+        .collect::<Vec<Record>>();
+
+    match format {
+        Format::Table => Ok(table(&people, config, instant)),
+        Format::Json => Ok(format!("{}\n", serde_json::to_string_pretty(&records)?)),
+        Format::Csv => delimited(&records, b','),
+        Format::Tsv => delimited(&records, b'\t'),
+        Format::Vcard => Ok(to_vcards(&people)),
+    }
+}
+
+/// The windows that everyone can meet in, as a table.
+fn plan(
+    people: &[Person],
+    config: &CompleteConfig,
+    instant: DateTime<Utc>,
+    days: i64,
+    minutes: i64,
+    limit: usize,
+) -> String {
+    let windows = find_windows(
+        people,
+        &config.frontend.working_hours,
+        instant,
+        days,
+        Duration::minutes(minutes),
+    );
+
+    if windows.is_empty() {
+        return format!(
+            "Working hours don't overlap for {minutes} minutes in the next {days} days.\n"
+        );
+    }
+
+    let rows = windows
+        .iter()
+        .take(limit)
+        .enumerate()
+        .map(|(i, window)| plan_row(i + 1, window, people))
+        .collect();
+
+    aligned(plan_headers(people), rows, &config.frontend.alignment)
+}
+
+/// Proposals as lines that can be imported, each with a comment explaining it.
+fn proposals_csv(proposals: &[Proposal]) -> Result<String> {
+    let mut output = String::new();
+
+    for proposal in proposals {
+        writeln!(
+            output,
+            "# <{}> {} of {} commits at {}",
+            proposal.email, proposal.matching, proposal.commits, proposal.timezone
+        )?;
+
+        // Quoted the same way they're read back in, including names that start like a comment.
+        let mut writer = csv::WriterBuilder::new()
+            .comment(Some(b'#'))
+            .from_writer(vec![]);
+
+        writer.write_record([proposal.name.clone(), proposal.timezone.to_string()])?;
+
+        output.push_str(&String::from_utf8(writer.into_inner()?)?);
+    }
+
+    Ok(output)
+}
+
+/// Imports a file, or standard input when there's no file.
+fn import(
+    storage: &mut Storage,
+    file: Option<PathBuf>,
+    format: ImportFormat,
+    conflict: Conflict,
+) -> Result<Summary> {
+    let summary = match (file, format) {
+        (Some(path), ImportFormat::Csv) => {
+            let file = File::open(&path)
+                .wrap_err_with(|| format!("Unable to open {}.", path.display()))?;
+
+            import_csv(storage, file, conflict)
+        }
+        (None, ImportFormat::Csv) => import_csv(storage, io::stdin().lock(), conflict),
+        (Some(path), ImportFormat::Vcard) => {
+            let text = read_to_string(&path)
+                .wrap_err_with(|| format!("Unable to read {}.", path.display()))?;
+
+            import_vcard(storage, &text, conflict)
+        }
+        (None, ImportFormat::Vcard) => {
+            let mut text = String::new();
+
+            io::stdin().read_to_string(&mut text)?;
+
+            import_vcard(storage, &text, conflict)
+        }
+    };
+
+    Ok(summary)
+}
+
+/// Reports the outcome of an import, failing if any of it couldn't be imported.
+fn report(summary: &Summary) -> Result<()> {
+    for (line, err) in &summary.errors {
+        eprintln!("Line {line}: {err}");
+    }
+
+    for (name, new_name) in &summary.renamed {
+        println!("Renamed {name} to {new_name}.");
+    }
+
+    if summary.without_timezone > 0 {
+        println!(
+            "Left out {} contacts without a timezone.",
+            summary.without_timezone
+        );
+    }
+
+    println!(
+        "Added {}, updated {}, renamed {}, skipped {}.",
+        summary.added,
+        summary.updated,
+        summary.renamed.len(),
+        summary.skipped
+    );
+
+    match summary.errors.len() {
+        0 => Ok(()),
+        1 => bail!("1 line could not be imported."),
+        n => bail!("{n} lines could not be imported."),
+    }
+}
+
+/// Adds everyone proposed from a git repository, the same as importing them.
+fn apply_proposals(storage: &mut Storage, proposals: Vec<Proposal>, conflict: Conflict) -> Summary {
+    let mut summary = Summary::default();
+
+    for proposal in proposals {
+        let outcome = insert(storage, &proposal.name, proposal.timezone, conflict);
+
+        summary.record(&proposal.name, outcome);
+    }
+
+    summary
+}
+
+/// Shows when someone works, or changes it when given new hours or told to use the default.
+fn change_hours(
+    storage: &mut Storage,
+    config: &CompleteConfig,
+    name: &str,
+    hours: Option<&str>,
+    default: bool,
+) -> Result<()> {
+    let default_hours = &config.frontend.working_hours;
+
+    let Some(person) = storage.find(name) else {
+        bail!("{name} is not being tracked.");
+    };
+
+    if hours.is_none() && !default {
+        match &person.hours {
+            Some(hours) => println!("{name} works {hours}."),
+            None => println!("{name} works {default_hours}, the default."),
+        }
+
+        return Ok(());
+    }
+
+    let hours = hours.map(str::parse::<WorkingHours>).transpose()?;
+
+    match &hours {
+        Some(hours) => println!("{name} now works {hours}."),
+        None => println!("{name} now works {default_hours}, the default."),
+    }
+
+    storage.set_hours(name, hours);
+    storage.dump_data();
+
+    Ok(())
+}
+
+/// Runs a single command without starting the interface.
+pub fn run(command: Command, config: &CompleteConfig) -> Result<()> {
+    let mut storage = Storage::new(config_path("storage.json"))?;
+
+    let now = Utc::now();
+    let time_format = config.frontend.time_format.as_str();
+
+    match command {
+        Command::List { format } => {
+            print!("{}", list(&storage, config, now, format)?);
+        }
+        Command::Add { name, timezone } => {
+            let timezone = timezone.parse::<Timezone>()?;
+
+            let verb = if storage.contains(&name) {
+                "Updated"
+            } else {
+                "Added"
+            };
+
+            storage.add(&name, timezone);
+            storage.dump_data();
+
+            println!("{verb} {name} at {timezone}.");
+        }
+        Command::Import {
+            file,
+            format,
+            on_conflict,
+        } => {
+            let format = format.unwrap_or_else(|| ImportFormat::guess(file.as_deref()));
+
+            let summary = import(&mut storage, file, format, on_conflict.into())?;
+
+            storage.dump_data();
+
+            report(&summary)?;
+        }
+        Command::FromGit {
+            repository,
+            since,
+            pick,
+            apply,
+            on_conflict,
+        } => {
+            let (proposals, rejected) = propose(
+                &parse_log(&read_log(&repository, since.as_deref())?),
+                pick.into(),
+            );
+
+            for (email, err) in rejected {
+                eprintln!("Left out <{email}>: {err}");
+            }
+
+            if !apply {
+                print!("{}", proposals_csv(&proposals)?);
+
+                return Ok(());
+            }
+
+            let summary = apply_proposals(&mut storage, proposals, on_conflict.into());
+
+            storage.dump_data();
+
+            report(&summary)?;
+        }
+        Command::Hours {
+            name,
+            hours,
+            default,
+        } => {
+            change_hours(&mut storage, config, &name, hours.as_deref(), default)?;
+        }
+        Command::Plan {
+            names,
+            days,
+            minutes,
+            limit,
+        } => {
+            let people = names
+                .iter()
+                .map(|name| {
+                    storage
+                        .find(name)
+                        .cloned()
+                        .ok_or_else(|| anyhow!("{name} is not being tracked."))
+                })
+                .collect::<Result<Vec<Person>>>()?;
+
+            print!("{}", plan(&people, config, now, days, minutes, limit));
+        }
+        Command::Remove { name } => {
+            if !storage.remove(&name) {
+                bail!("{name} is not being tracked.");
+            }
+
+            storage.dump_data();
+
+            println!("Removed {name}.");
+        }
+        Command::Now { name } => {
+            let place = Place::resolve(&name, |name| storage.find(name).map(|p| p.timezone))?;
+
+            println!("{}", place.local_time_at(now).format(time_format));
+        }
+        Command::Convert { time, from, to } => {
+            let lookup = |name: &str| storage.find(name).map(|p| p.timezone);
+
+            let from = Place::resolve(&from, lookup)?;
+            let to = Place::resolve(&to, lookup)?;
+
+            let local = parse_local_time(&time, from.local_time_at(now).date())?;
+
+            let instant = from.instant_from_local(local)?;
+
+            println!("{}", to.local_time_at(instant).format(time_format));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::handlers::storage::TempStorage;
+
+    #[test]
+    fn test_list_is_aligned_like_the_table() {
+        let mut storage = TempStorage::new("list");
+
+        storage.add("Alice", Timezone::Offset(60));
+        storage.add("Bob", Timezone::Offset(-270));
+
+        let mut config = CompleteConfig::default();
+        config.frontend.time_format = "%H:%M".to_string();
+
+        let instant = Utc.with_ymd_and_hms(2023, 10, 20, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            list(&storage, &config, instant, Format::Table).unwrap(),
+            " User Offset  Time    Status             Next\n\
+             Alice +01:00 13:00   working       ends in 4h\n  \
+               Bob -04:30 07:30 off-hours starts in 1h 30m\n"
+        );
+    }
+
+    #[test]
+    fn test_list_as_csv_and_tsv() {
+        let mut storage = TempStorage::new("csv");
+
+        storage.add("Alice, from Berlin", "Europe/Berlin".parse().unwrap());
+        storage.add("Bob", Timezone::Offset(-270));
+
+        let config = CompleteConfig::default();
+
+        let instant = Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            list(&storage, &config, instant, Format::Csv).unwrap(),
+            "user,timezone,offset,offset_seconds,time,status,status_changes_in_seconds\n\
+             \"Alice, from Berlin\",Europe/Berlin,+02:00,7200,2023-07-01T14:00:00+02:00,off-hours,154800\n\
+             Bob,-04:30,-04:30,-16200,2023-07-01T07:30:00-04:30,off-hours,178200\n"
+        );
+        assert_eq!(
+            list(&storage, &config, instant, Format::Tsv).unwrap(),
+            "user\ttimezone\toffset\toffset_seconds\ttime\tstatus\tstatus_changes_in_seconds\n\
+             Alice, from Berlin\tEurope/Berlin\t+02:00\t7200\t2023-07-01T14:00:00+02:00\toff-hours\t154800\n\
+             Bob\t-04:30\t-04:30\t-16200\t2023-07-01T07:30:00-04:30\toff-hours\t178200\n"
+        );
+    }
+
+    #[test]
+    fn test_list_as_json() {
+        let mut storage = TempStorage::new("json");
+
+        storage.add("Alice", Timezone::Offset(0));
+
+        let config = CompleteConfig::default();
+
+        let instant = Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap();
+
+        let output = list(&storage, &config, instant, Format::Json).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&output).unwrap(),
+            serde_json::json!([{
+                "user": "Alice",
+                "timezone": "+00:00",
+                "offset": "+00:00",
+                "offset_seconds": 0,
+                "time": "2023-07-01T12:00:00+00:00",
+                "status": "off-hours",
+                "status_changes_in_seconds": 162_000,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_list_uses_own_working_hours() {
+        let mut storage = TempStorage::new("hours");
+
+        storage.add("Alice", Timezone::Offset(0));
+        storage.set_hours("Alice", Some("Sat 10:00-14:00".parse().unwrap()));
+
+        let config = CompleteConfig::default();
+
+        let instant = Utc.with_ymd_and_hms(2023, 7, 1, 12, 0, 0).unwrap();
+
+        let record = Record::new(
+            &storage.find("Alice").unwrap().clone(),
+            instant,
+            &config.frontend.working_hours,
+        );
+
+        assert_eq!(record.status, "working");
+        assert_eq!(record.status_changes_in_seconds, Some(7200));
+    }
+
+    #[test]
+    fn test_plan_rejects_out_of_range_lengths() {
+        let parse =
+            |args: &[&str]| Cli::try_parse_from(["tzt", "plan", "Alice"].iter().chain(args));
+
+        assert!(parse(&["--days", "366", "--minutes", "1440"]).is_ok());
+        assert!(parse(&["--days", "-3"]).is_err());
+        assert!(parse(&["--days", "300000000"]).is_err());
+        assert!(parse(&["--minutes", "0"]).is_err());
+        assert!(parse(&["--minutes", "1441"]).is_err());
+    }
+
+    #[test]
+    fn test_proposals_can_be_imported() {
+        let proposal = |name: &str| Proposal {
+            name: name.to_string(),
+            email: "jane@example.com".to_string(),
+            timezone: Timezone::Offset(-150),
+            commits: 3,
+            matching: 2,
+        };
+
+        let names = ["Doe, \"JJ\" Jane", "#1 Jane", "Jane\nDoe"];
+
+        let text = proposals_csv(&names.map(proposal)).unwrap();
+
+        assert!(text.starts_with(
+            "# <jane@example.com> 2 of 3 commits at -02:30\n\"Doe, \"\"JJ\"\" Jane\",-02:30\n"
+        ));
+
+        let mut storage = TempStorage::new("proposals");
+
+        let summary = import_csv(&mut storage, text.as_bytes(), Conflict::Skip);
+
+        assert_eq!(summary.added, 3);
+        assert!(summary.errors.is_empty());
+
+        for name in names {
+            assert_eq!(storage.find(name).unwrap().timezone, Timezone::Offset(-150));
+        }
+    }
+}
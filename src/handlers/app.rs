@@ -1,39 +1,392 @@
+use std::{collections::BTreeSet, fmt};
+
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
 use rustyline::line_buffer::LineBuffer;
-use tui::style::Style;
+use tui::widgets::TableState;
 
 use crate::{
-    handlers::storage::Storage,
-    utils::{pathing::config_path, styles},
+    handlers::{
+        config::CompleteConfig,
+        keybindings::Keybindings,
+        storage::{Person, Storage},
+    },
+    utils::{
+        availability::WorkingHours,
+        pathing::config_path,
+        planner::{find_windows, Window, DEFAULT_DAYS, DEFAULT_MINUTES},
+        search::matches_person,
+        sorting::Sort,
+        styles::Theme,
+        timezones::parse_conversion,
+    },
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum State {
     Normal,
     Input,
-    // Help,
+    /// Changing the name or timezone of the selected person.
+    Edit,
+    /// Typing something to narrow down the rows of the table.
+    Filter,
+    /// Waiting for the removal of the selected person to be confirmed.
+    Delete,
+    /// Showing which keys can be pressed in the previous state.
+    Help,
+    /// Showing when the people being planned for can meet.
+    Plan,
+    /// Typing a time to show everyone's clock at.
+    Convert,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Normal => write!(f, "table"),
+            Self::Input => write!(f, "adding"),
+            Self::Edit => write!(f, "editing"),
+            Self::Filter => write!(f, "filtering"),
+            Self::Delete => write!(f, "deleting"),
+            Self::Help => write!(f, "help"),
+            Self::Plan => write!(f, "meeting planner"),
+            Self::Convert => write!(f, "converting a time"),
+        }
+    }
+}
+
+/// How everyone being tracked is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// Everyone's offset, time, and availability.
+    Table,
+    /// The hours around the time being viewed, shaded by when everyone is around.
+    Timeline,
+    /// How many people are working during each hour of the UTC day.
+    Coverage,
+}
+
+impl View {
+    /// The view after this one, going back to the first after the last.
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Table => Self::Timeline,
+            Self::Timeline => Self::Coverage,
+            Self::Coverage => Self::Table,
+        }
+    }
+
+    /// Whether everyone has a row that can be selected, rather than being counted together.
+    pub const fn has_rows(self) -> bool {
+        !matches!(self, Self::Coverage)
+    }
+}
+
+impl fmt::Display for View {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Table => write!(f, "table"),
+            Self::Timeline => write!(f, "timeline"),
+            Self::Coverage => write!(f, "coverage"),
+        }
+    }
+}
+
+/// What would happen if the input box was submitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validity {
+    /// Nothing has been typed yet.
+    Empty,
+    /// Someone new would be added, or the person being edited would be changed.
+    Valid,
+    /// Someone that's already being tracked would have their timezone replaced.
+    Exists,
+    /// The name belongs to someone other than the person being edited.
+    Taken,
+    /// The text can't be read as a name and a timezone, for the given reason.
+    Invalid(String),
+}
+
+impl Validity {
+    /// Spells out the validity, so that it doesn't rely on colors alone.
+    pub fn marker(&self) -> &str {
+        match self {
+            Self::Empty => "",
+            Self::Valid => "ok",
+            Self::Exists => "exists, will be replaced",
+            Self::Taken => "name is taken",
+            Self::Invalid(reason) => reason,
+        }
+    }
 }
 
 pub struct App {
     /// State of the application.
     pub state: State,
+    /// The state to return to after the help popup is closed.
+    pub previous_state: State,
+    /// Which keys trigger which actions.
+    pub keybindings: Keybindings,
+    /// The styles that everything is drawn with.
+    pub theme: Theme,
     /// Storing information of timezones in a file.
     pub storage: Storage,
     /// The single box for inserting information into.
     pub input_buffer: LineBuffer,
+    /// What the rows of the table are narrowed down by.
+    pub filter_buffer: LineBuffer,
+    /// The time being typed in to be converted.
+    pub convert_buffer: LineBuffer,
     /// The different validity states that the input buffer contains.
-    pub buffer_validity: Style,
+    pub buffer_validity: Validity,
+    /// Which row of the table is selected.
+    pub table_state: TableState,
+    /// How many rows of the table fit on the screen, as of the last draw.
+    pub page_size: usize,
+    /// The id of the person being changed while in the edit state.
+    pub editing: Option<u64>,
+    /// How the rows of the table are ordered.
+    pub sort: Sort,
+    /// How everyone being tracked is shown.
+    pub view: View,
+    /// The ids of the people marked for a meeting.
+    pub marked: BTreeSet<u64>,
+    /// The ids of the people in the meeting being planned.
+    pub planning: Vec<u64>,
+    /// Which row of the table of meeting windows is selected.
+    pub plan_state: TableState,
+    /// How many rows the table of meeting windows has, as of the last draw.
+    pub plan_length: usize,
+    /// Which row of the help popup is selected.
+    pub help_state: TableState,
+    /// How many bindings the help popup lists, as of the last draw.
+    pub help_length: usize,
+    /// The instant being viewed instead of now, after converting a time.
+    pub pinned: Option<DateTime<Utc>>,
+    /// How far the time being viewed is moved from now, or from the pinned instant.
+    pub time_shift: Duration,
 }
 
 impl App {
-    pub fn new() -> Self {
-        Self {
+    pub fn new(config: &CompleteConfig) -> Result<Self> {
+        let theme = Theme::new(&config.theme);
+
+        let mut app = Self {
             state: State::Normal,
-            storage: Storage::new(config_path("storage.json")),
+            previous_state: State::Normal,
+            keybindings: config.keybindings.clone(),
+            theme,
+            storage: Storage::new(config_path("storage.json"))?,
             input_buffer: LineBuffer::with_capacity(4096),
-            buffer_validity: styles::COLUMN_TITLE,
+            filter_buffer: LineBuffer::with_capacity(4096),
+            convert_buffer: LineBuffer::with_capacity(4096),
+            buffer_validity: Validity::Empty,
+            table_state: TableState::default(),
+            page_size: 1,
+            editing: None,
+            sort: Sort::new(config.frontend.sort_key, config.frontend.sort_direction),
+            view: View::Table,
+            marked: BTreeSet::new(),
+            planning: vec![],
+            plan_state: TableState::default(),
+            plan_length: 0,
+            help_state: TableState::default(),
+            help_length: 0,
+            pinned: None,
+            time_shift: Duration::zero(),
+        };
+
+        app.move_selection(0);
+
+        Ok(app)
+    }
+
+    /// The instant that everyone's clock is shown at, given what the time is now.
+    pub fn viewed_instant_from(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.pinned.unwrap_or(now) + self.time_shift
+    }
+
+    /// The instant that everyone's clock is shown at.
+    pub fn viewed_instant(&self) -> DateTime<Utc> {
+        self.viewed_instant_from(Utc::now())
+    }
+
+    /// Whether the current time is being shown.
+    pub const fn is_live(&self) -> bool {
+        self.pinned.is_none() && self.time_shift.is_zero()
+    }
+
+    /// Does something that can move people around the table, keeping the same person selected.
+    fn keep_selection(&mut self, change: impl FnOnce(&mut Self)) {
+        let selected = self.selected_person().map(|p| p.id);
+
+        change(self);
+
+        if let Some(id) = selected {
+            self.table_state
+                .select(self.visible_people().iter().position(|p| p.id == id));
         }
     }
 
+    /// Views a time further from the one being viewed, or closer to it.
+    pub fn shift_time(&mut self, amount: Duration) {
+        self.keep_selection(|app| app.time_shift += amount);
+    }
+
+    /// Views a specific instant, or follows the current time again.
+    pub fn view_at(&mut self, instant: Option<DateTime<Utc>>) {
+        self.keep_selection(|app| {
+            app.pinned = instant;
+            app.time_shift = Duration::zero();
+        });
+    }
+
+    /// The instant that the contents of the convert box stand for.
+    pub fn conversion(&self) -> Result<DateTime<Utc>> {
+        parse_conversion(
+            self.convert_buffer.as_str(),
+            |name| self.storage.find(name).map(|p| p.timezone),
+            Utc::now(),
+        )
+    }
+
+    /// Everyone that matches the filter, in the order they appear in the table.
+    pub fn visible_people(&self) -> Vec<Person> {
+        let mut people = self
+            .storage
+            .get_all()
+            .into_iter()
+            .filter(|p| matches_person(self.filter_buffer.as_str(), p))
+            .collect::<Vec<Person>>();
+
+        self.sort.apply(&mut people, self.viewed_instant());
+
+        people
+    }
+
+    /// The person on the currently selected row of the table.
+    pub fn selected_person(&self) -> Option<Person> {
+        self.table_state
+            .selected()
+            .and_then(|i| self.visible_people().get(i).cloned())
+    }
+
+    /// Switches to the next sort mode, keeping the same person selected.
+    pub fn cycle_sort(&mut self) {
+        self.keep_selection(|app| app.sort = app.sort.next());
+    }
+
+    /// Fills the input box with the selected person's details, ready to be changed.
+    pub fn start_editing(&mut self) {
+        if let Some(person) = self.selected_person() {
+            let text = format!("{},{}", person.name, person.timezone);
+
+            self.input_buffer.update(&text, text.len());
+            self.editing = Some(person.id);
+            self.state = State::Edit;
+        }
+    }
+
+    /// Marks the selected person for a meeting, or unmarks them if they already are.
+    pub fn toggle_mark(&mut self) {
+        if let Some(person) = self.selected_person() {
+            if !self.marked.remove(&person.id) {
+                self.marked.insert(person.id);
+            }
+        }
+    }
+
+    /// Plans a meeting with everyone that's marked, or with everyone in the table if nobody is.
+    pub fn start_planning(&mut self) {
+        self.planning = self
+            .visible_people()
+            .iter()
+            .filter(|p| self.marked.is_empty() || self.marked.contains(&p.id))
+            .map(|p| p.id)
+            .collect();
+
+        self.state = State::Plan;
+        self.plan_state.select(None);
+        self.move_selection(0);
+    }
+
+    /// The people in the meeting being planned, in the order they appear in the table.
+    pub fn planned_people(&self) -> Vec<Person> {
+        self.planning
+            .iter()
+            .filter_map(|id| self.storage.get(*id).cloned())
+            .collect()
+    }
+
+    /// When the people in the meeting being planned can meet, least inconvenient first.
+    pub fn plan_windows(
+        &self,
+        default_hours: &WorkingHours,
+        instant: DateTime<Utc>,
+    ) -> Vec<Window> {
+        find_windows(
+            &self.planned_people(),
+            default_hours,
+            instant,
+            DEFAULT_DAYS,
+            Duration::minutes(DEFAULT_MINUTES),
+        )
+    }
+
+    /// How many rows the table of the current state has, and which of them is selected.
+    fn current_table(&mut self) -> (usize, &mut TableState) {
+        match self.state {
+            State::Plan => (self.plan_length, &mut self.plan_state),
+            State::Help => (self.help_length, &mut self.help_state),
+            _ => (self.visible_people().len(), &mut self.table_state),
+        }
+    }
+
+    /// Moves the selection by some amount of rows, stopping at either end of the table.
+    pub fn move_selection(&mut self, amount: isize) {
+        let (length, table_state) = self.current_table();
+
+        move_within(table_state, length, amount);
+    }
+
+    /// Keeps the selected meeting window within the table, which can shrink between draws.
+    pub fn keep_plan_selection(&mut self) {
+        move_within(&mut self.plan_state, self.plan_length, 0);
+    }
+
+    /// Moves the selection up or down by however many rows fit on the screen.
+    pub fn move_selection_pages(&mut self, pages: isize) {
+        let page_size = isize::try_from(self.page_size.max(1)).unwrap_or(isize::MAX);
+
+        self.move_selection(pages.saturating_mul(page_size));
+    }
+
+    pub fn select_first(&mut self) {
+        self.current_table().1.select(None);
+        self.move_selection(0);
+    }
+
+    pub fn select_last(&mut self) {
+        self.current_table().1.select(Some(usize::MAX));
+        self.move_selection(0);
+    }
+
     pub fn cleanup(&self) {
         self.storage.dump_data();
     }
 }
+
+/// Moves the selection of a table with some amount of rows, stopping at either end.
+fn move_within(table_state: &mut TableState, length: usize, amount: isize) {
+    if length == 0 {
+        table_state.select(None);
+
+        return;
+    }
+
+    let selected = table_state
+        .selected()
+        .map_or(0, |i| i.saturating_add_signed(amount));
+
+    table_state.select(Some(selected.min(length - 1)));
+}
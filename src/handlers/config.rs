@@ -5,9 +5,13 @@ use std::{
 };
 
 use color_eyre::eyre::{bail, Error, Result};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tui::style::{Color, Modifier};
 
-use crate::utils::pathing::config_path;
+use crate::{
+    handlers::keybindings::Keybindings,
+    utils::{availability::WorkingHours, pathing::config_path},
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(default)]
@@ -16,6 +20,10 @@ pub struct CompleteConfig {
     pub terminal: TerminalConfig,
     /// How everything looks to the user.
     pub frontend: FrontendConfig,
+    /// Which keys trigger which actions.
+    pub keybindings: Keybindings,
+    /// The colors and modifiers of everything that's drawn.
+    pub theme: ThemeConfig,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,33 +32,160 @@ pub struct TerminalConfig {
     pub tick_delay: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum Alignment {
     Left,
+    #[default]
     Right,
     Center,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// The order in which people were added.
+    #[default]
+    Added,
+    Name,
+    Offset,
+    /// The time of day on everyone's clock.
+    Time,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct FrontendConfig {
-    /// The format of the date and time outputs. Formats can be found at https://strftime.org/.
+    /// The format of the date and time outputs. Formats can be found at <https://strftime.org/>.
     pub time_format: String,
     /// Which side the information should be aligned to.
     pub alignment: Alignment,
     /// The margin between the main window and the terminal border.
     pub margin: u16,
+    /// What the rows of the table are sorted by when starting up.
+    pub sort_key: SortKey,
+    /// Whether the rows of the table are sorted in ascending or descending order.
+    pub sort_direction: SortDirection,
+    /// When people work, unless they have hours of their own.
+    pub working_hours: WorkingHours,
 }
 
-impl Default for TerminalConfig {
-    fn default() -> Self {
-        Self { tick_delay: 30 }
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// A color by name such as `lightcyan`, by terminal palette index such as `208`,
+/// or in truecolor hex such as `#ff8700`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThemeColor(pub Color);
+
+impl Serialize for ThemeColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.to_string().to_lowercase())
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+
+        text.parse()
+            .map(Self)
+            .map_err(|_| serde::de::Error::custom(format!("Unknown color {text}.")))
     }
 }
 
-impl Default for Alignment {
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeModifier {
+    Bold,
+    Dim,
+    Italic,
+    Underlined,
+    SlowBlink,
+    RapidBlink,
+    Reversed,
+    Hidden,
+    CrossedOut,
+}
+
+impl From<ThemeModifier> for Modifier {
+    fn from(modifier: ThemeModifier) -> Self {
+        match modifier {
+            ThemeModifier::Bold => Self::BOLD,
+            ThemeModifier::Dim => Self::DIM,
+            ThemeModifier::Italic => Self::ITALIC,
+            ThemeModifier::Underlined => Self::UNDERLINED,
+            ThemeModifier::SlowBlink => Self::SLOW_BLINK,
+            ThemeModifier::RapidBlink => Self::RAPID_BLINK,
+            ThemeModifier::Reversed => Self::REVERSED,
+            ThemeModifier::Hidden => Self::HIDDEN,
+            ThemeModifier::CrossedOut => Self::CROSSED_OUT,
+        }
+    }
+}
+
+/// Changes to a single style of the preset, anything left out is kept as it was.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct StyleConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<ThemeColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<ThemeColor>,
+    /// Replaces the modifiers of the preset, an empty list removes all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modifiers: Option<Vec<ThemeModifier>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// The colors to start from: dark, light, or high-contrast.
+    pub preset: ThemePreset,
+    /// Draws with modifiers such as bold and italic instead of colors.
+    /// Also turned on by setting the `NO_COLOR` environment variable.
+    pub monochrome: bool,
+    /// The borders of the table and the help popup.
+    pub border: StyleConfig,
+    /// The column headers of the table.
+    pub column_title: StyleConfig,
+    /// The selected row of the table.
+    pub selected: StyleConfig,
+    /// The values in the title of the table, such as the local time.
+    pub title: StyleConfig,
+    /// What's being typed into the input and filter boxes.
+    pub text: StyleConfig,
+    /// An input box with someone new in it.
+    pub valid: StyleConfig,
+    /// An input box with someone that's already being tracked in it.
+    pub exists: StyleConfig,
+    /// An input box that can't be submitted, and the delete confirmation.
+    pub invalid: StyleConfig,
+    /// Rows of people that are working.
+    pub working: StyleConfig,
+    /// Rows of people that are awake, but not working.
+    pub off_hours: StyleConfig,
+    /// Rows of people that are most likely asleep.
+    pub sleeping: StyleConfig,
+}
+
+impl Default for TerminalConfig {
     fn default() -> Self {
-        Self::Right
+        Self { tick_delay: 30 }
     }
 }
 
@@ -60,6 +195,9 @@ impl Default for FrontendConfig {
             time_format: "%c".to_string(),
             alignment: Alignment::Right,
             margin: 0,
+            sort_key: SortKey::Added,
+            sort_direction: SortDirection::Ascending,
+            working_hours: WorkingHours::default(),
         }
     }
 }
@@ -79,7 +217,9 @@ impl CompleteConfig {
 
             bail!("Configuration was generated at {path_str}, please fill it out with necessary information.")
         } else if let Ok(config_contents) = read_to_string(p) {
-            let config: Self = toml::from_str(config_contents.as_str()).unwrap();
+            let config: Self = toml::from_str(config_contents.as_str())?;
+
+            config.keybindings.check_conflicts()?;
 
             Ok(config)
         } else {
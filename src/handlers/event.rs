@@ -1,9 +1,11 @@
-use std::time::Duration;
+use std::{fmt, str::FromStr, time::Duration};
 
-use crossterm::event::{self, Event as CEvent, KeyCode, KeyModifiers};
+use color_eyre::eyre::{bail, Error, Result};
+use crossterm::event::{self, Event as CEvent, KeyCode, KeyModifiers, MouseEventKind};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tokio::{sync::mpsc, time::Instant};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Key {
     Backspace,
     Esc,
@@ -20,14 +22,107 @@ pub enum Key {
     Tab,
     BackTab,
     Enter,
+    F(u8),
     Char(char),
     Ctrl(char),
     Alt(char),
     Null,
 }
 
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::F(n) => write!(f, "F{n}"),
+            Self::Char(' ') => write!(f, "Space"),
+            Self::Char(c) => write!(f, "{c}"),
+            Self::Ctrl(c) => write!(f, "Ctrl+{c}"),
+            Self::Alt(c) => write!(f, "Alt+{c}"),
+            _ => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = Error;
+
+    /// The inverse of displaying a key, such as `q`, `Ctrl+f`, `Alt+b`, `F1`, or `PageUp`.
+    /// Names and modifiers are case insensitive, single characters are not.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chars = s.chars();
+
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Self::Char(c));
+        }
+
+        let lowercase = s.to_lowercase();
+
+        let single_char = |rest: &str| {
+            let mut chars = rest.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        };
+
+        if let Some(c) = lowercase.strip_prefix("ctrl+").and_then(single_char) {
+            return Ok(Self::Ctrl(c));
+        }
+
+        if let Some(c) = lowercase.strip_prefix("alt+").and_then(single_char) {
+            return Ok(Self::Alt(c));
+        }
+
+        if let Some(n) = lowercase.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            return Ok(Self::F(n));
+        }
+
+        Ok(match lowercase.as_str() {
+            "backspace" => Self::Backspace,
+            "esc" => Self::Esc,
+            "up" => Self::Up,
+            "down" => Self::Down,
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "home" => Self::Home,
+            "end" => Self::End,
+            "delete" => Self::Delete,
+            "insert" => Self::Insert,
+            "pageup" => Self::PageUp,
+            "pagedown" => Self::PageDown,
+            "tab" => Self::Tab,
+            "backtab" => Self::BackTab,
+            "enter" => Self::Enter,
+            "space" => Self::Char(' '),
+            _ => bail!("Unknown key {s}."),
+        })
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Mouse {
+    ScrollUp,
+    ScrollDown,
+    Null,
+}
+
 pub enum Event<I> {
     Input(I),
+    Mouse(Mouse),
     Tick,
 }
 
@@ -37,7 +132,6 @@ pub struct Events {
 
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
-    pub exit_key: Key,
     pub tick_rate: Duration,
 }
 
@@ -55,7 +149,9 @@ impl Events {
                     .unwrap_or_else(|| Duration::from_secs(0));
 
                 if event::poll(timeout).unwrap() {
-                    if let Ok(CEvent::Key(key)) = event::read() {
+                    let read_event = event::read();
+
+                    if let Ok(CEvent::Key(key)) = read_event {
                         let key = match key.code {
                             KeyCode::Backspace => Key::Backspace,
                             KeyCode::Esc => Key::Esc,
@@ -72,6 +168,7 @@ impl Events {
                             KeyCode::Tab => Key::Tab,
                             KeyCode::BackTab => Key::BackTab,
                             KeyCode::Enter => Key::Enter,
+                            KeyCode::F(n) => Key::F(n),
                             KeyCode::Char(c) => match key.modifiers {
                                 KeyModifiers::NONE | KeyModifiers::SHIFT => Key::Char(c),
                                 KeyModifiers::CONTROL => Key::Ctrl(c),
@@ -84,6 +181,16 @@ impl Events {
                             eprintln!("{err}");
                             return;
                         }
+                    } else if let Ok(CEvent::Mouse(mouse)) = read_event {
+                        let mouse = match mouse.kind {
+                            MouseEventKind::ScrollUp => Mouse::ScrollUp,
+                            MouseEventKind::ScrollDown => Mouse::ScrollDown,
+                            _ => Mouse::Null,
+                        };
+                        if let Err(err) = tx.send(Event::Mouse(mouse)).await {
+                            eprintln!("{err}");
+                            return;
+                        }
                     }
                 }
 
@@ -104,3 +211,41 @@ impl Events {
         self.rx.recv().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_from_str() {
+        assert_eq!("q".parse::<Key>().unwrap(), Key::Char('q'));
+        assert_eq!("G".parse::<Key>().unwrap(), Key::Char('G'));
+        assert_eq!("Ctrl+f".parse::<Key>().unwrap(), Key::Ctrl('f'));
+        assert_eq!("alt+B".parse::<Key>().unwrap(), Key::Alt('b'));
+        assert_eq!("F12".parse::<Key>().unwrap(), Key::F(12));
+        assert_eq!("pagedown".parse::<Key>().unwrap(), Key::PageDown);
+        assert_eq!("Space".parse::<Key>().unwrap(), Key::Char(' '));
+    }
+
+    #[test]
+    fn test_key_from_str_unknown() {
+        let err = "Hyper+x".parse::<Key>().unwrap_err();
+
+        assert_eq!(err.to_string(), "Unknown key Hyper+x.");
+    }
+
+    #[test]
+    fn test_key_display_round_trips() {
+        for key in [
+            Key::Char('?'),
+            Key::Char(' '),
+            Key::Ctrl('w'),
+            Key::Alt('t'),
+            Key::F(1),
+            Key::BackTab,
+            Key::Enter,
+        ] {
+            assert_eq!(key.to_string().parse::<Key>().unwrap(), key);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::io::Read;
+
+use crate::{
+    handlers::storage::Storage,
+    utils::{
+        timezones::{parse_user_timezone, Timezone},
+        vcard::parse_vcards,
+    },
+};
+
+/// What happens when someone being imported has the same name as someone already tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Conflict {
+    /// Leave the person already being tracked as they are.
+    #[default]
+    Skip,
+    /// Replace the timezone of the person already being tracked.
+    Overwrite,
+    /// Track them as someone new, with a number after their name.
+    Rename,
+}
+
+/// What happened to a single person being imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Added,
+    Updated,
+    Skipped,
+    /// Added under a different name, because theirs was taken.
+    Renamed(String),
+}
+
+/// Everything that happened during an import.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub renamed: Vec<(String, String)>,
+    /// Contacts that were left out, because they don't have a timezone.
+    pub without_timezone: usize,
+    /// The lines that couldn't be imported, and why.
+    pub errors: Vec<(u64, String)>,
+}
+
+impl Summary {
+    pub fn record(&mut self, name: &str, outcome: Outcome) {
+        match outcome {
+            Outcome::Added => self.added += 1,
+            Outcome::Updated => self.updated += 1,
+            Outcome::Skipped => self.skipped += 1,
+            Outcome::Renamed(new_name) => self.renamed.push((name.to_string(), new_name)),
+        }
+    }
+}
+
+/// Adds someone to the storage, deciding what to do if their name is taken.
+pub fn insert(
+    storage: &mut Storage,
+    name: &str,
+    timezone: Timezone,
+    conflict: Conflict,
+) -> Outcome {
+    if !storage.contains(name) {
+        storage.add(name, timezone);
+
+        return Outcome::Added;
+    }
+
+    match conflict {
+        Conflict::Skip => Outcome::Skipped,
+        Conflict::Overwrite => {
+            storage.add(name, timezone);
+
+            Outcome::Updated
+        }
+        Conflict::Rename => {
+            let mut n = 2;
+
+            while storage.contains(&format!("{name} ({n})")) {
+                n += 1;
+            }
+
+            let new_name = format!("{name} ({n})");
+
+            storage.add(&new_name, timezone);
+
+            Outcome::Renamed(new_name)
+        }
+    }
+}
+
+/// Imports lines of `name,timezone`, the same as typing each of them into the input box.
+/// Names can be quoted to contain commas, columns past the second are ignored, and a
+/// header such as the one from listing as CSV is skipped.
+pub fn import_csv<R: Read>(storage: &mut Storage, reader: R, conflict: Conflict) -> Summary {
+    let mut summary = Summary::default();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .from_reader(reader);
+
+    for (i, record) in reader.records().enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err.position().map_or(0, csv::Position::line);
+
+                summary.errors.push((line, err.to_string()));
+
+                continue;
+            }
+        };
+
+        let line = record.position().map_or(0, csv::Position::line);
+
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        // Quoted names are taken as they are, even with line breaks that typing couldn't give.
+        let parsed = match (record.get(0), record.get(1)) {
+            (Some(name), Some(timezone)) if !name.is_empty() && !timezone.is_empty() => timezone
+                .parse::<Timezone>()
+                .map(|timezone| (name.to_string(), timezone)),
+            _ => parse_user_timezone(&record.iter().take(2).collect::<Vec<&str>>().join(",")),
+        };
+
+        match parsed {
+            Ok((name, timezone)) => {
+                let outcome = insert(storage, &name, timezone, conflict);
+
+                summary.record(&name, outcome);
+            }
+            Err(_) if i == 0 && is_header(&record[0]) => {}
+            Err(err) => summary.errors.push((line, err.to_string())),
+        }
+    }
+
+    summary
+}
+
+/// Imports every contact of a vCard file that has a timezone.
+/// Errors are reported on the line that the contact starts on.
+pub fn import_vcard(storage: &mut Storage, text: &str, conflict: Conflict) -> Summary {
+    let mut summary = Summary::default();
+
+    for contact in parse_vcards(text) {
+        let Some(name) = contact.name else {
+            summary
+                .errors
+                .push((contact.line, "Contact has no name.".to_string()));
+
+            continue;
+        };
+
+        let Some(timezone) = contact.timezone else {
+            summary.without_timezone += 1;
+
+            continue;
+        };
+
+        match timezone.parse::<Timezone>() {
+            Ok(timezone) => {
+                let outcome = insert(storage, &name, timezone, conflict);
+
+                summary.record(&name, outcome);
+            }
+            Err(err) => summary
+                .errors
+                .push((contact.line, format!("{name}: {err}"))),
+        }
+    }
+
+    summary
+}
+
+fn is_header(first_column: &str) -> bool {
+    ["user", "name"]
+        .iter()
+        .any(|header| first_column.eq_ignore_ascii_case(header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::storage::TempStorage;
+
+    #[test]
+    fn test_import_reports_errors_per_line() {
+        let mut storage = TempStorage::new("errors");
+
+        let text = "Alice,+3\n\nBob, Europe/Berlin\nCarol\n\"Doe, Jane\",-04:30\nDave,+99\n";
+
+        let summary = import_csv(&mut storage, text.as_bytes(), Conflict::Skip);
+
+        assert_eq!(summary.added, 3);
+        assert_eq!(
+            summary.errors,
+            vec![
+                (
+                    4,
+                    "Missing a comma between the name and the timezone.".to_string()
+                ),
+                (
+                    6,
+                    "Offset +99:00 is out of range, it must be between -12:00 and +14:00."
+                        .to_string()
+                ),
+            ]
+        );
+        assert!(storage.contains("Doe, Jane"));
+    }
+
+    #[test]
+    fn test_import_skips_listing_header() {
+        let mut storage = TempStorage::new("header");
+
+        let text = "user,timezone,offset,offset_seconds,time\n\
+                    Alice,Europe/Berlin,+02:00,7200,2023-07-01T14:00:00+02:00\n";
+
+        let summary = import_csv(&mut storage, text.as_bytes(), Conflict::Skip);
+
+        assert_eq!(summary.added, 1);
+        assert!(summary.errors.is_empty());
+    }
+
+    #[test]
+    fn test_import_vcard() {
+        let mut storage = TempStorage::new("vcard");
+
+        let text = "BEGIN:VCARD\nFN:Alice\nTZ:Europe/Berlin\nEND:VCARD\n\
+                    BEGIN:VCARD\nFN:Bob\nEND:VCARD\n\
+                    BEGIN:VCARD\nFN:Carol\nTZ:Mars/Olympus\nEND:VCARD\n";
+
+        let summary = import_vcard(&mut storage, text, Conflict::Skip);
+
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.without_timezone, 1);
+        assert_eq!(
+            summary.errors,
+            vec![(8, "Carol: Unknown timezone Mars/Olympus.".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_import_conflicts() {
+        let mut storage = TempStorage::new("conflicts");
+
+        storage.add("Alice", Timezone::Offset(0));
+        storage.add("Alice (2)", Timezone::Offset(0));
+
+        let text: &[u8] = b"Alice,+3\n";
+
+        let skipped = import_csv(&mut storage, text, Conflict::Skip);
+        assert_eq!(skipped.skipped, 1);
+        assert_eq!(storage.find("Alice").unwrap().timezone, Timezone::Offset(0));
+
+        let renamed = import_csv(&mut storage, text, Conflict::Rename);
+        assert_eq!(
+            renamed.renamed,
+            vec![("Alice".to_string(), "Alice (3)".to_string())]
+        );
+        assert_eq!(
+            storage.find("Alice (3)").unwrap().timezone,
+            Timezone::Offset(180)
+        );
+
+        let overwritten = import_csv(&mut storage, text, Conflict::Overwrite);
+        assert_eq!(overwritten.updated, 1);
+        assert_eq!(
+            storage.find("Alice").unwrap().timezone,
+            Timezone::Offset(180)
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::{BTreeMap, HashMap};
+
+use color_eyre::eyre::{bail, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::handlers::{app::State, event::Key};
+
+/// Everything that can be done by pressing a key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Help,
+    Insert,
+    Edit,
+    Delete,
+    Filter,
+    CycleSort,
+    SelectPrevious,
+    SelectNext,
+    PageUp,
+    PageDown,
+    SelectFirst,
+    SelectLast,
+    Mark,
+    Plan,
+    LaterMinutes,
+    EarlierMinutes,
+    LaterHours,
+    EarlierHours,
+    LaterDays,
+    EarlierDays,
+    Live,
+    Convert,
+    CycleView,
+    Submit,
+    Cancel,
+    MoveForward,
+    MoveBackward,
+    MoveHome,
+    MoveEnd,
+    MoveNextWord,
+    MovePreviousWord,
+    TransposeChars,
+    TransposeWords,
+    DiscardLine,
+    KillLine,
+    DeletePreviousWord,
+    DeleteNextChar,
+    Backspace,
+}
+
+impl Action {
+    /// What the action does, as shown in the help popup.
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::Help => "Show this help",
+            Self::Insert => "Add someone",
+            Self::Edit => "Edit the selected person",
+            Self::Delete => "Delete the selected person",
+            Self::Filter => "Filter the table",
+            Self::CycleSort => "Change the sort order",
+            Self::SelectPrevious => "Select the previous row",
+            Self::SelectNext => "Select the next row",
+            Self::PageUp => "Go up a page",
+            Self::PageDown => "Go down a page",
+            Self::SelectFirst => "Select the first row",
+            Self::SelectLast => "Select the last row",
+            Self::Mark => "Mark the selected person for a meeting",
+            Self::Plan => "Plan a meeting with the marked people, or everyone shown",
+            Self::LaterMinutes => "View the time 15 minutes later",
+            Self::EarlierMinutes => "View the time 15 minutes earlier",
+            Self::LaterHours => "View the time an hour later",
+            Self::EarlierHours => "View the time an hour earlier",
+            Self::LaterDays => "View the time a day later",
+            Self::EarlierDays => "View the time a day earlier",
+            Self::Live => "Go back to the current time",
+            Self::Convert => "Show everyone's time at a time such as Fri 15:00 @Alice",
+            Self::CycleView => "Switch between the table, timeline, and coverage chart",
+            Self::Submit => "Submit",
+            Self::Cancel => "Cancel",
+            Self::MoveForward => "Move forward a character",
+            Self::MoveBackward => "Move back a character",
+            Self::MoveHome => "Move to the start of the line",
+            Self::MoveEnd => "Move to the end of the line",
+            Self::MoveNextWord => "Move forward a word",
+            Self::MovePreviousWord => "Move back a word",
+            Self::TransposeChars => "Swap the characters around the cursor",
+            Self::TransposeWords => "Swap the words around the cursor",
+            Self::DiscardLine => "Delete everything before the cursor",
+            Self::KillLine => "Delete everything after the cursor",
+            Self::DeletePreviousWord => "Delete the previous word",
+            Self::DeleteNextChar => "Delete the character under the cursor",
+            Self::Backspace => "Delete the previous character",
+        }
+    }
+
+    /// The name of the action in the configuration file, such as `move_forward`.
+    /// Taken from serde, so that it's always the same as what the configuration is read with.
+    pub fn name(self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => name,
+            _ => unreachable!("Actions are serialized as their names."),
+        }
+    }
+}
+
+/// Which keys trigger which actions, for a single state of the application.
+pub type Bindings = BTreeMap<Action, Vec<Key>>;
+
+/// Every binding, grouped by the states they're active in.
+/// This is what both the key dispatch and the help popup are built from.
+#[derive(Serialize, Debug, Clone)]
+pub struct Keybindings {
+    pub normal: Bindings,
+    /// Shared by every state that types into a line of text.
+    pub input: Bindings,
+    pub delete: Bindings,
+    pub help: Bindings,
+    /// Looking through the times that a meeting could be held at.
+    pub plan: Bindings,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            normal: BTreeMap::from([
+                (Action::Quit, vec![Key::Char('q')]),
+                (Action::Help, vec![Key::Char('?'), Key::F(1)]),
+                (Action::Insert, vec![Key::Char('i')]),
+                (Action::Edit, vec![Key::Char('e')]),
+                (Action::Delete, vec![Key::Char('d'), Key::Delete]),
+                (Action::Filter, vec![Key::Char('/')]),
+                (Action::CycleSort, vec![Key::Char('s')]),
+                (Action::SelectPrevious, vec![Key::Char('k'), Key::Up]),
+                (Action::SelectNext, vec![Key::Char('j'), Key::Down]),
+                (Action::PageUp, vec![Key::PageUp]),
+                (Action::PageDown, vec![Key::PageDown]),
+                (Action::SelectFirst, vec![Key::Char('g'), Key::Home]),
+                (Action::SelectLast, vec![Key::Char('G'), Key::End]),
+                (Action::Mark, vec![Key::Char(' ')]),
+                (Action::Plan, vec![Key::Char('p')]),
+                (Action::LaterMinutes, vec![Key::Char('l'), Key::Right]),
+                (Action::EarlierMinutes, vec![Key::Char('h'), Key::Left]),
+                (Action::LaterHours, vec![Key::Char('L')]),
+                (Action::EarlierHours, vec![Key::Char('H')]),
+                (Action::LaterDays, vec![Key::Char('>')]),
+                (Action::EarlierDays, vec![Key::Char('<')]),
+                (Action::Live, vec![Key::Char('t')]),
+                (Action::Convert, vec![Key::Char('c')]),
+                (Action::CycleView, vec![Key::Char('v')]),
+            ]),
+            input: BTreeMap::from([
+                (Action::Help, vec![Key::F(1)]),
+                (Action::Submit, vec![Key::Enter]),
+                (Action::Cancel, vec![Key::Esc]),
+                (Action::MoveForward, vec![Key::Ctrl('f'), Key::Right]),
+                (Action::MoveBackward, vec![Key::Ctrl('b'), Key::Left]),
+                (Action::MoveHome, vec![Key::Ctrl('a'), Key::Home]),
+                (Action::MoveEnd, vec![Key::Ctrl('e'), Key::End]),
+                (Action::MoveNextWord, vec![Key::Alt('f')]),
+                (Action::MovePreviousWord, vec![Key::Alt('b')]),
+                (Action::TransposeChars, vec![Key::Ctrl('t')]),
+                (Action::TransposeWords, vec![Key::Alt('t')]),
+                (Action::DiscardLine, vec![Key::Ctrl('u')]),
+                (Action::KillLine, vec![Key::Ctrl('k')]),
+                (Action::DeletePreviousWord, vec![Key::Ctrl('w')]),
+                (Action::DeleteNextChar, vec![Key::Ctrl('d')]),
+                (Action::Backspace, vec![Key::Backspace, Key::Delete]),
+            ]),
+            delete: BTreeMap::from([
+                (Action::Help, vec![Key::Char('?'), Key::F(1)]),
+                (Action::Submit, vec![Key::Char('y'), Key::Enter]),
+                (Action::Cancel, vec![Key::Char('n'), Key::Esc]),
+            ]),
+            help: BTreeMap::from([
+                (
+                    Action::Cancel,
+                    vec![Key::Esc, Key::Char('q'), Key::Char('?'), Key::F(1)],
+                ),
+                (Action::SelectPrevious, vec![Key::Char('k'), Key::Up]),
+                (Action::SelectNext, vec![Key::Char('j'), Key::Down]),
+                (Action::PageUp, vec![Key::PageUp]),
+                (Action::PageDown, vec![Key::PageDown]),
+                (Action::SelectFirst, vec![Key::Char('g'), Key::Home]),
+                (Action::SelectLast, vec![Key::Char('G'), Key::End]),
+            ]),
+            plan: BTreeMap::from([
+                (Action::Help, vec![Key::Char('?'), Key::F(1)]),
+                (
+                    Action::Cancel,
+                    vec![Key::Esc, Key::Char('q'), Key::Char('p')],
+                ),
+                (Action::SelectPrevious, vec![Key::Char('k'), Key::Up]),
+                (Action::SelectNext, vec![Key::Char('j'), Key::Down]),
+                (Action::PageUp, vec![Key::PageUp]),
+                (Action::PageDown, vec![Key::PageDown]),
+                (Action::SelectFirst, vec![Key::Char('g'), Key::Home]),
+                (Action::SelectLast, vec![Key::Char('G'), Key::End]),
+            ]),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keybindings {
+    /// Actions that are left out of the configuration keep their default keys,
+    /// an empty list of keys unbinds an action.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize, Default)]
+        #[serde(default)]
+        struct Overrides {
+            normal: Bindings,
+            input: Bindings,
+            delete: Bindings,
+            help: Bindings,
+            plan: Bindings,
+        }
+
+        let overrides = Overrides::deserialize(deserializer)?;
+
+        let mut keybindings = Self::default();
+
+        keybindings.normal.extend(overrides.normal);
+        keybindings.input.extend(overrides.input);
+        keybindings.delete.extend(overrides.delete);
+        keybindings.help.extend(overrides.help);
+        keybindings.plan.extend(overrides.plan);
+
+        Ok(keybindings)
+    }
+}
+
+impl Keybindings {
+    /// Makes sure that no key triggers more than one action in the same state.
+    pub fn check_conflicts(&self) -> Result<()> {
+        for (context, bindings) in [
+            ("normal", &self.normal),
+            ("input", &self.input),
+            ("delete", &self.delete),
+            ("help", &self.help),
+            ("plan", &self.plan),
+        ] {
+            let mut seen = HashMap::new();
+
+            for (action, keys) in bindings {
+                for key in keys {
+                    if let Some(other) = seen.insert(key.to_string(), *action) {
+                        if other != *action {
+                            bail!(
+                                "{key} is bound to both {} and {} in [keybindings.{context}].",
+                                other.name(),
+                                action.name()
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The bindings that are active in a state.
+    pub const fn for_state(&self, state: State) -> &Bindings {
+        match state {
+            State::Normal => &self.normal,
+            State::Input | State::Edit | State::Filter | State::Convert => &self.input,
+            State::Delete => &self.delete,
+            State::Help => &self.help,
+            State::Plan => &self.plan,
+        }
+    }
+
+    /// The action a key triggers in a state, if any.
+    pub fn action(&self, state: State, key: Key) -> Option<Action> {
+        self.for_state(state)
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Every action of a state next to the keys that trigger it, ready to be displayed.
+    pub fn help(&self, state: State) -> Vec<(String, &'static str)> {
+        self.for_state(state)
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                (keys, action.description())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_lookup_depends_on_state() {
+        let keybindings = Keybindings::default();
+
+        assert_eq!(
+            keybindings.action(State::Normal, Key::Char('q')),
+            Some(Action::Quit)
+        );
+        assert_eq!(keybindings.action(State::Input, Key::Char('q')), None);
+        assert_eq!(
+            keybindings.action(State::Filter, Key::Ctrl('w')),
+            Some(Action::DeletePreviousWord)
+        );
+        assert_eq!(
+            keybindings.action(State::Delete, Key::Char('y')),
+            Some(Action::Submit)
+        );
+        assert_eq!(
+            keybindings.action(State::Normal, Key::Char('p')),
+            Some(Action::Plan)
+        );
+        assert_eq!(
+            keybindings.action(State::Plan, Key::Char('p')),
+            Some(Action::Cancel)
+        );
+    }
+
+    #[test]
+    fn test_help_lists_every_binding_of_a_state() {
+        let keybindings = Keybindings::default();
+
+        let help = keybindings.help(State::Delete);
+
+        assert_eq!(
+            help,
+            vec![
+                ("?, F1".to_string(), "Show this help"),
+                ("y, Enter".to_string(), "Submit"),
+                ("n, Esc".to_string(), "Cancel"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_action_name_matches_configuration() {
+        assert_eq!(Action::Quit.name(), "quit");
+        assert_eq!(Action::DeletePreviousWord.name(), "delete_previous_word");
+    }
+
+    #[test]
+    fn test_defaults_have_no_conflicts() {
+        assert!(Keybindings::default().check_conflicts().is_ok());
+    }
+
+    #[test]
+    fn test_overrides_keep_other_defaults() {
+        let keybindings: Keybindings = toml::from_str(
+            r#"
+            [normal]
+            quit = ["x", "Ctrl+c"]
+            help = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            keybindings.action(State::Normal, Key::Ctrl('c')),
+            Some(Action::Quit)
+        );
+        assert_eq!(keybindings.action(State::Normal, Key::Char('q')), None);
+        assert_eq!(keybindings.action(State::Normal, Key::F(1)), None);
+        assert_eq!(
+            keybindings.action(State::Normal, Key::Char('i')),
+            Some(Action::Insert)
+        );
+        assert_eq!(keybindings.input, Keybindings::default().input);
+    }
+
+    #[test]
+    fn test_conflicting_keys_are_rejected() {
+        let keybindings: Keybindings = toml::from_str(
+            r#"
+            [input]
+            move_forward = ["Ctrl+w"]
+            "#,
+        )
+        .unwrap();
+
+        let err = keybindings.check_conflicts().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Ctrl+w is bound to both move_forward and delete_previous_word in [keybindings.input]."
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let result = toml::from_str::<Keybindings>(
+            r#"
+            [normal]
+            quit = ["Hyper+q"]
+            "#,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_serialized_defaults_load_back() {
+        let text = toml::to_string(&Keybindings::default()).unwrap();
+
+        let keybindings: Keybindings = toml::from_str(&text).unwrap();
+
+        assert_eq!(keybindings.normal, Keybindings::default().normal);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod app;
 pub mod config;
 pub mod event;
+pub mod import;
+pub mod keybindings;
 pub mod storage;
@@ -1,69 +1,477 @@
 use std::{
-    collections::HashMap,
-    fs::{read_to_string, File},
+    collections::BTreeMap,
+    fs::{copy, read_to_string, rename, File},
     io::Write,
     path::Path,
 };
 
-type StorageMap = HashMap<String, i64>;
+use color_eyre::eyre::{anyhow, bail, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::{availability::WorkingHours, timezones::Timezone};
+
+/// The version of the storage format written by this build.
+/// Files without a version are the original flat map of names to hour offsets.
+const STORAGE_VERSION: u64 = 1;
+
+/// Someone whose timezone is being tracked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Person {
+    /// Stays the same for as long as the person is stored.
+    pub id: u64,
+    /// The name shown in the table.
+    pub name: String,
+    /// Where in the world they are.
+    pub timezone: Timezone,
+    /// Anything else worth remembering about them.
+    #[serde(default)]
+    pub notes: String,
+    /// Labels for grouping people together.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When they usually work, if it differs from the configured default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<WorkingHours>,
+}
+
+#[cfg(test)]
+impl Person {
+    /// Someone with only a name and a timezone, and an id that no other test person has.
+    pub fn for_test(name: &str, timezone: Timezone) -> Self {
+        static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+        Self {
+            id: NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            name: name.to_string(),
+            timezone,
+            notes: String::new(),
+            tags: vec![],
+            hours: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StorageFile {
+    version: u64,
+    /// The id given to the next person added, so that nobody gets the id of someone removed.
+    /// Files written before it was kept start after the highest id in them.
+    #[serde(default)]
+    next_id: u64,
+    people: Vec<Person>,
+}
 
 #[derive(Debug)]
 pub struct Storage {
-    items: StorageMap,
+    people: Vec<Person>,
+    next_id: u64,
     file_path: String,
 }
 
+/// Reads the contents of a storage file, upgrading older formats to the current one.
+/// The returned boolean is true if a migration took place.
+fn parse_storage(content: &str) -> Result<(StorageFile, bool)> {
+    let value: Value = serde_json::from_str(content).wrap_err("Storage is not valid JSON.")?;
+
+    let version = match value.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow!("Storage version must be an integer."))?,
+        None => 0,
+    };
+
+    match version {
+        0 => {
+            let items: BTreeMap<String, Timezone> =
+                serde_json::from_value(value).wrap_err("Unable to read version 0 storage.")?;
+
+            let people: Vec<Person> = items
+                .into_iter()
+                .zip(1..)
+                .map(|((name, timezone), id)| Person {
+                    id,
+                    name,
+                    timezone,
+                    notes: String::new(),
+                    tags: vec![],
+                    hours: None,
+                })
+                .collect();
+
+            let file = StorageFile {
+                version: STORAGE_VERSION,
+                next_id: people.len() as u64 + 1,
+                people,
+            };
+
+            Ok((file, true))
+        }
+        STORAGE_VERSION => {
+            let mut file: StorageFile = serde_json::from_value(value)
+                .wrap_err_with(|| format!("Unable to read version {version} storage."))?;
+
+            let after_highest = file.people.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+
+            file.next_id = file.next_id.max(after_highest);
+
+            Ok((file, false))
+        }
+        _ => bail!("Storage version {version} is newer than this build supports."),
+    }
+}
+
 impl Storage {
-    pub fn new(file_path: String) -> Self {
+    pub fn new(file_path: String) -> Result<Self> {
         if !Path::new(&file_path).exists() {
-            let items = StorageMap::new();
+            let storage = Self {
+                people: vec![],
+                next_id: 1,
+                file_path,
+            };
 
-            let storage_str = serde_json::to_string(&items).unwrap();
+            storage.dump_data();
 
-            let mut file = File::create(&file_path).unwrap();
+            return Ok(storage);
+        }
 
-            file.write_all(storage_str.as_bytes()).unwrap();
+        let file_content = read_to_string(&file_path)?;
 
-            return Self { items, file_path };
-        }
+        let (file, migrated) = parse_storage(&file_content)
+            .wrap_err_with(|| format!("Could not load storage at {file_path}."))?;
+
+        let storage = Self {
+            people: file.people,
+            next_id: file.next_id,
+            file_path,
+        };
 
-        let file_content = read_to_string(&file_path).unwrap();
+        if migrated {
+            copy(&storage.file_path, format!("{}.bak", storage.file_path))?;
 
-        let items: StorageMap = serde_json::from_str(&file_content).unwrap();
+            storage.dump_data();
+        }
 
-        Self { items, file_path }
+        Ok(storage)
     }
 
-    /// The storage map kept in memory is dumped into the file specified at initialization
-    /// of this structure.
+    /// The storage kept in memory is dumped into the file specified at initialization
+    /// of this structure. The data is written to a temporary file first, so that the
+    /// original is never left half-written.
     pub fn dump_data(&self) {
-        let storage_str = serde_json::to_string(&self.items).unwrap();
+        let file = StorageFile {
+            version: STORAGE_VERSION,
+            next_id: self.next_id,
+            people: self.people.clone(),
+        };
 
-        let mut file = File::create(&self.file_path).unwrap();
+        let storage_str = serde_json::to_string_pretty(&file).unwrap();
+
+        let temp_path = format!("{}.tmp", self.file_path);
+
+        let mut file = File::create(&temp_path).unwrap();
 
         file.write_all(storage_str.as_bytes()).unwrap();
+        file.sync_all().unwrap();
+
+        rename(&temp_path, &self.file_path).unwrap();
+    }
+
+    /// Checks if someone with this name exists in the storage.
+    pub fn contains(&self, name: &str) -> bool {
+        self.people.iter().any(|p| p.name == name)
+    }
+
+    /// Adds a person to the storage.
+    /// If someone with this name already exists, their timezone is overwritten.
+    pub fn add(&mut self, name: &str, timezone: Timezone) {
+        if let Some(person) = self.people.iter_mut().find(|p| p.name == name) {
+            person.timezone = timezone;
+
+            return;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.people.push(Person {
+            id,
+            name: name.to_string(),
+            timezone,
+            notes: String::new(),
+            tags: vec![],
+            hours: None,
+        });
+    }
+
+    /// Renames someone and changes their timezone in one go.
+    /// If the new name belongs to someone else, nothing is changed.
+    pub fn update(&mut self, id: u64, name: &str, timezone: Timezone) -> Result<()> {
+        if self.people.iter().any(|p| p.name == name && p.id != id) {
+            bail!("{name} is already being tracked.");
+        }
+
+        let person = self
+            .people
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| anyhow!("Nobody with the id {id} is being tracked."))?;
+
+        person.name = name.to_string();
+        person.timezone = timezone;
+
+        Ok(())
+    }
+
+    /// Removes a person from the storage, returning whether they existed.
+    /// If they don't exist, nothing is changed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let length = self.people.len();
+
+        self.people.retain(|p| p.name != name);
+
+        self.people.len() != length
+    }
+
+    /// Changes when someone works, returning whether they exist.
+    /// Hours that are left out fall back to the configured default.
+    pub fn set_hours(&mut self, name: &str, hours: Option<WorkingHours>) -> bool {
+        let Some(person) = self.people.iter_mut().find(|p| p.name == name) else {
+            return false;
+        };
+
+        person.hours = hours;
+
+        true
+    }
+
+    /// Finds someone by their id.
+    pub fn get(&self, id: u64) -> Option<&Person> {
+        self.people.iter().find(|p| p.id == id)
+    }
+
+    /// Finds someone by their name.
+    pub fn find(&self, name: &str) -> Option<&Person> {
+        self.people.iter().find(|p| p.name == name)
+    }
+
+    /// Simply get everyone from the storage, in the order they were added.
+    pub fn get_all(&self) -> Vec<Person> {
+        self.people.clone()
+    }
+}
+
+/// Where a storage file used by a test is kept, unique to the test run so runs don't collide.
+#[cfg(test)]
+fn temp_storage_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("tzt-{}-{name}.json", std::process::id()))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// An empty storage for a test, whose file is removed again once the test is done with it.
+#[cfg(test)]
+pub struct TempStorage(Storage);
+
+#[cfg(test)]
+impl TempStorage {
+    /// The name has to be unique among tests.
+    pub fn new(name: &str) -> Self {
+        let path = temp_storage_path(name);
+
+        let _ = std::fs::remove_file(&path);
+
+        Self(Storage::new(path).unwrap())
+    }
+
+    /// A storage read from a file that starts out with the given contents.
+    pub fn with_contents(name: &str, contents: &str) -> Self {
+        let path = temp_storage_path(name);
+
+        std::fs::write(&path, contents).unwrap();
+
+        Self(Storage::new(path).unwrap())
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempStorage {
+    type Target = Storage;
+
+    fn deref(&self) -> &Storage {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl std::ops::DerefMut for TempStorage {
+    fn deref_mut(&mut self) -> &mut Storage {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempStorage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0.file_path);
+        let _ = std::fs::remove_file(format!("{}.bak", self.0.file_path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_storage_migrates_flat_hour_offsets() {
+        let (file, migrated) = parse_storage(r#"{"Bob": -4, "Alice": 2}"#).unwrap();
+        let people = file.people;
+
+        assert!(migrated);
+        assert_eq!(file.next_id, 3);
+        assert_eq!(people.len(), 2);
+        assert_eq!(people[0].id, 1);
+        assert_eq!(people[0].name, "Alice");
+        assert_eq!(people[0].timezone, Timezone::Offset(120));
+        assert_eq!(people[1].id, 2);
+        assert_eq!(people[1].name, "Bob");
+        assert_eq!(people[1].timezone, Timezone::Offset(-240));
+    }
+
+    #[test]
+    fn test_parse_storage_migrates_flat_named_zones() {
+        let (file, migrated) = parse_storage(r#"{"Alice": "Europe/Berlin"}"#).unwrap();
+        let people = file.people;
+
+        assert!(migrated);
+        assert_eq!(
+            people[0].timezone,
+            Timezone::Named(chrono_tz::Europe::Berlin)
+        );
+    }
+
+    #[test]
+    fn test_parse_storage_migrates_empty_flat_map() {
+        let (file, migrated) = parse_storage("{}").unwrap();
+        let people = file.people;
+
+        assert!(migrated);
+        assert!(people.is_empty());
+    }
+
+    #[test]
+    fn test_parse_storage_current_version() {
+        let content = r#"{
+            "version": 1,
+            "people": [
+                {"id": 7, "name": "Alice", "timezone": "+05:30", "notes": "Lead", "tags": ["ops"], "hours": "Sun-Thu 08:00-16:00"},
+                {"id": 3, "name": "Bob", "timezone": "America/Sao_Paulo"}
+            ]
+        }"#;
+
+        let (file, migrated) = parse_storage(content).unwrap();
+        let people = file.people;
+
+        assert!(!migrated);
+        assert_eq!(file.next_id, 8);
+        assert_eq!(people[0].id, 7);
+        assert_eq!(people[0].timezone, Timezone::Offset(330));
+        assert_eq!(people[0].notes, "Lead");
+        assert_eq!(people[0].tags, vec!["ops".to_string()]);
+        assert_eq!(
+            people[0].hours,
+            Some("Sun-Thu 08:00-16:00".parse().unwrap())
+        );
+        assert_eq!(people[1].name, "Bob");
+        assert!(people[1].tags.is_empty());
+        assert!(people[1].hours.is_none());
+    }
+
+    #[test]
+    fn test_parse_storage_newer_version() {
+        let err = parse_storage(r#"{"version": 99, "people": []}"#).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Storage version 99 is newer than this build supports."
+        );
     }
 
-    /// Checks if the key passed in exists in the storage map.
-    pub fn contains(&self, key: &str) -> bool {
-        self.items.contains_key(key)
+    #[test]
+    fn test_storage_new_upgrades_flat_file() {
+        let storage =
+            TempStorage::with_contents("upgrade", r#"{"Alice": 2, "Bob": "Australia/Sydney"}"#);
+        let path = &storage.file_path;
+
+        let (file, migrated) = parse_storage(&read_to_string(path).unwrap()).unwrap();
+        let people = file.people;
+        let backup = read_to_string(format!("{path}.bak")).unwrap();
+
+        assert!(!migrated);
+        assert_eq!(people, storage.get_all());
+        assert_eq!(backup, r#"{"Alice": 2, "Bob": "Australia/Sydney"}"#);
+    }
+
+    #[test]
+    fn test_storage_add_keeps_ids_and_insertion_order() {
+        let mut storage = TempStorage::new("add");
+        storage.add("Zed", Timezone::Offset(0));
+        storage.add("Alice", Timezone::Offset(60));
+        storage.add("Zed", Timezone::Offset(120));
+        storage.remove("Alice");
+        storage.add("Bob", Timezone::Offset(180));
+
+        let people = storage.get_all();
+
+        assert_eq!(people.len(), 2);
+        assert_eq!((people[0].id, people[0].name.as_str()), (1, "Zed"));
+        assert_eq!(people[0].timezone, Timezone::Offset(120));
+        assert_eq!((people[1].id, people[1].name.as_str()), (3, "Bob"));
     }
 
-    /// Adds a key-value pair to the storage map.
-    /// If the entry already exists, the value is overwritten.
-    pub fn add(&mut self, key: &str, value: i64) {
-        self.items.insert(key.to_string(), value);
+    #[test]
+    fn test_storage_never_reuses_ids() {
+        let mut storage = TempStorage::new("reuse");
+        storage.add("Alice", Timezone::Offset(0));
+        storage.add("Bob", Timezone::Offset(0));
+        storage.remove("Bob");
+        storage.dump_data();
+
+        let mut reopened = Storage::new(storage.file_path.clone()).unwrap();
+        reopened.add("Carol", Timezone::Offset(0));
+
+        assert_eq!(reopened.find("Carol").unwrap().id, 3);
     }
 
-    /// Removes a key-value pair from the storage map.
-    /// If the entry doesn't exist, nothing is changed.
-    #[allow(dead_code)]
-    pub fn remove(&mut self, key: &str) {
-        self.items.remove(key);
+    #[test]
+    fn test_storage_update_renames() {
+        let mut storage = TempStorage::new("rename");
+        storage.add("Alice", Timezone::Offset(0));
+
+        storage
+            .update(1, "Alicia", Timezone::Named(chrono_tz::Europe::Berlin))
+            .unwrap();
+
+        let person = storage.get(1).unwrap();
+
+        assert!(!storage.contains("Alice"));
+        assert_eq!(person.name, "Alicia");
+        assert_eq!(person.timezone, Timezone::Named(chrono_tz::Europe::Berlin));
     }
 
-    /// Simply get all the items from the storage.
-    pub fn get_all(&self) -> StorageMap {
-        self.items.clone()
+    #[test]
+    fn test_storage_update_rejects_taken_name() {
+        let mut storage = TempStorage::new("taken");
+        storage.add("Alice", Timezone::Offset(0));
+        storage.add("Bob", Timezone::Offset(60));
+
+        let err = storage
+            .update(2, "Alice", Timezone::Offset(120))
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Alice is already being tracked.");
+        assert_eq!(storage.get(2).unwrap().name, "Bob");
+        assert_eq!(storage.get(2).unwrap().timezone, Timezone::Offset(60));
     }
 }
@@ -11,11 +11,14 @@
     clippy::use_self
 )]
 
+mod cli;
 mod handlers;
 mod terminal;
 mod ui;
 mod utils;
 
+use clap::Parser;
+use cli::Cli;
 use color_eyre::eyre::{Result, WrapErr};
 use handlers::{app::App, config::CompleteConfig};
 
@@ -23,11 +26,28 @@ use handlers::{app::App, config::CompleteConfig};
 async fn main() -> Result<()> {
     color_eyre::install().unwrap();
 
-    let config = CompleteConfig::new()
-        .wrap_err("Configuration error.")
-        .unwrap();
+    let cli = Cli::parse();
 
-    let app = App::new();
+    let config = CompleteConfig::new().wrap_err("Configuration error.");
+
+    if let Some(command) = cli.command {
+        if let Err(err) = config.and_then(|config| cli::run(command, &config)) {
+            let causes = err
+                .chain()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>();
+
+            eprintln!("{}", causes.join(" "));
+
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    let config = config.unwrap();
+
+    let app = App::new(&config).wrap_err("Storage error.").unwrap();
 
     terminal::ui_driver(config, app).await;
 
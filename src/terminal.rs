@@ -1,21 +1,21 @@
-use std::{
-    io::{stdout, Stdout},
-    time::Duration,
-};
+use std::io::{stdout, Stdout};
+
+use chrono::Duration;
 
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use rustyline::{At, Word};
+use rustyline::{line_buffer::LineBuffer, At, Word};
 use tui::{backend::CrosstermBackend, Terminal};
 
 use crate::{
     handlers::{
         app::{App, State},
         config::CompleteConfig,
-        event::{self, Event, Key},
+        event::{self, Event, Key, Mouse},
+        keybindings::Action,
     },
     ui::draw_ui,
     utils::timezones::parse_user_timezone,
@@ -51,6 +51,290 @@ fn quit_terminal(mut terminal: Terminal<CrosstermBackend<Stdout>>) {
     terminal.show_cursor().unwrap();
 }
 
+/// Adds someone new, or changes the person being edited, with the contents of the input box.
+fn submit_input(app: &mut App) {
+    let Ok((user, timezone)) = parse_user_timezone(app.input_buffer.as_str()) else {
+        return;
+    };
+
+    if let Some(id) = app.editing {
+        if app.storage.update(id, &user, timezone).is_err() {
+            return;
+        }
+
+        app.storage.dump_data();
+
+        app.editing = None;
+        app.state = State::Normal;
+    } else {
+        app.storage.add(&user, timezone);
+        app.storage.dump_data();
+
+        app.move_selection(0);
+    }
+
+    app.input_buffer.update("", 0);
+}
+
+/// The Emacs-style actions for moving around and changing a line of text.
+/// Characters that aren't bound to anything are typed into the line.
+fn edit_line(buffer: &mut LineBuffer, key: Key, action: Option<Action>) {
+    match action {
+        Some(Action::MoveForward) => {
+            buffer.move_forward(1);
+        }
+        Some(Action::MoveBackward) => {
+            buffer.move_backward(1);
+        }
+        Some(Action::MoveHome) => {
+            buffer.move_home();
+        }
+        Some(Action::MoveEnd) => {
+            buffer.move_end();
+        }
+        Some(Action::MoveNextWord) => {
+            buffer.move_to_next_word(At::AfterEnd, Word::Emacs, 1);
+        }
+        Some(Action::MovePreviousWord) => {
+            buffer.move_to_prev_word(Word::Emacs, 1);
+        }
+        Some(Action::TransposeChars) => {
+            buffer.transpose_chars();
+        }
+        Some(Action::TransposeWords) => {
+            buffer.transpose_words(1);
+        }
+        Some(Action::DiscardLine) => {
+            buffer.discard_line();
+        }
+        Some(Action::KillLine) => {
+            buffer.kill_line();
+        }
+        Some(Action::DeletePreviousWord) => {
+            buffer.delete_prev_word(Word::Emacs, 1);
+        }
+        Some(Action::DeleteNextChar) => {
+            buffer.delete(1);
+        }
+        Some(Action::Backspace) => {
+            buffer.backspace(1);
+        }
+        None => {
+            if let Key::Char(c) = key {
+                buffer.insert(c, 1);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_normal_action(app: &mut App, action: Action) {
+    match action {
+        // Nobody can be picked out of the coverage chart, so nothing happens to whoever was.
+        Action::SelectPrevious
+        | Action::SelectNext
+        | Action::PageUp
+        | Action::PageDown
+        | Action::SelectFirst
+        | Action::SelectLast
+        | Action::CycleSort
+        | Action::Edit
+        | Action::Delete
+        | Action::Mark
+            if !app.view.has_rows() => {}
+        Action::Insert => {
+            app.state = State::Input;
+        }
+        Action::SelectPrevious => {
+            app.move_selection(-1);
+        }
+        Action::SelectNext => {
+            app.move_selection(1);
+        }
+        Action::PageUp => {
+            app.move_selection_pages(-1);
+        }
+        Action::PageDown => {
+            app.move_selection_pages(1);
+        }
+        Action::SelectFirst => {
+            app.select_first();
+        }
+        Action::SelectLast => {
+            app.select_last();
+        }
+        Action::CycleSort => {
+            app.cycle_sort();
+        }
+        Action::Filter => {
+            app.state = State::Filter;
+        }
+        Action::Edit => {
+            app.start_editing();
+        }
+        Action::Delete if app.selected_person().is_some() => {
+            app.state = State::Delete;
+        }
+        Action::Mark => {
+            app.toggle_mark();
+        }
+        Action::Plan => {
+            app.start_planning();
+        }
+        Action::LaterMinutes => {
+            app.shift_time(Duration::minutes(15));
+        }
+        Action::EarlierMinutes => {
+            app.shift_time(Duration::minutes(-15));
+        }
+        Action::LaterHours => {
+            app.shift_time(Duration::hours(1));
+        }
+        Action::EarlierHours => {
+            app.shift_time(Duration::hours(-1));
+        }
+        Action::LaterDays => {
+            app.shift_time(Duration::days(1));
+        }
+        Action::EarlierDays => {
+            app.shift_time(Duration::days(-1));
+        }
+        Action::Live => {
+            app.view_at(None);
+        }
+        Action::Convert => {
+            app.state = State::Convert;
+        }
+        Action::CycleView => {
+            app.view = app.view.next();
+        }
+        _ => {}
+    }
+}
+
+/// Looking through the times that the people being planned for can meet.
+fn handle_plan_action(app: &mut App, action: Action) {
+    match action {
+        Action::SelectPrevious => {
+            app.move_selection(-1);
+        }
+        Action::SelectNext => {
+            app.move_selection(1);
+        }
+        Action::PageUp => {
+            app.move_selection_pages(-1);
+        }
+        Action::PageDown => {
+            app.move_selection_pages(1);
+        }
+        Action::SelectFirst => {
+            app.select_first();
+        }
+        Action::SelectLast => {
+            app.select_last();
+        }
+        Action::Cancel => {
+            app.state = State::Normal;
+        }
+        _ => {}
+    }
+}
+
+fn handle_help_action(app: &mut App, action: Action) {
+    match action {
+        Action::SelectPrevious => {
+            app.move_selection(-1);
+        }
+        Action::SelectNext => {
+            app.move_selection(1);
+        }
+        Action::PageUp => {
+            app.move_selection_pages(-1);
+        }
+        Action::PageDown => {
+            app.move_selection_pages(1);
+        }
+        Action::SelectFirst => {
+            app.select_first();
+        }
+        Action::SelectLast => {
+            app.select_last();
+        }
+        _ => {}
+    }
+}
+
+fn handle_delete_action(app: &mut App, action: Action) {
+    match action {
+        Action::Submit => {
+            if let Some(person) = app.selected_person() {
+                app.storage.remove(&person.name);
+                app.storage.dump_data();
+
+                app.marked.remove(&person.id);
+            }
+
+            app.move_selection(0);
+            app.state = State::Normal;
+        }
+        Action::Cancel => {
+            app.state = State::Normal;
+        }
+        _ => {}
+    }
+}
+
+/// Editing the contents of the input box, and submitting it.
+fn handle_input_key(app: &mut App, key: Key, action: Option<Action>) {
+    match action {
+        Some(Action::Submit) => {
+            submit_input(app);
+        }
+        Some(Action::Cancel) => {
+            app.input_buffer.update("", 0);
+            app.editing = None;
+            app.state = State::Normal;
+        }
+        _ => edit_line(&mut app.input_buffer, key, action),
+    }
+}
+
+/// Narrowing down the rows of the table as the filter is typed.
+fn handle_filter_key(app: &mut App, key: Key, action: Option<Action>) {
+    match action {
+        Some(Action::Submit) => {
+            app.state = State::Normal;
+        }
+        Some(Action::Cancel) => {
+            app.filter_buffer.update("", 0);
+            app.select_first();
+            app.state = State::Normal;
+        }
+        _ => {
+            edit_line(&mut app.filter_buffer, key, action);
+            app.select_first();
+        }
+    }
+}
+
+/// Typing a time, and showing everyone's clock at it once submitted.
+fn handle_convert_key(app: &mut App, key: Key, action: Option<Action>) {
+    match action {
+        Some(Action::Submit) => {
+            if let Ok(instant) = app.conversion() {
+                app.view_at(Some(instant));
+                app.convert_buffer.update("", 0);
+                app.state = State::Normal;
+            }
+        }
+        Some(Action::Cancel) => {
+            app.convert_buffer.update("", 0);
+            app.state = State::Normal;
+        }
+        _ => edit_line(&mut app.convert_buffer, key, action),
+    }
+}
+
 pub async fn ui_driver(config: CompleteConfig, mut app: App) {
     let original_hook = std::panic::take_hook();
 
@@ -60,93 +344,57 @@ pub async fn ui_driver(config: CompleteConfig, mut app: App) {
     }));
 
     let mut events = event::Events::with_config(event::Config {
-        exit_key: Key::Null,
-        tick_rate: Duration::from_millis(100),
+        tick_rate: std::time::Duration::from_millis(100),
     });
 
     let mut terminal = init_terminal();
 
     terminal.clear().unwrap();
 
-    'outer: loop {
+    loop {
         terminal
             .draw(|frame| draw_ui(frame, &mut app, &config))
             .unwrap();
 
-        if let Some(Event::Input(key)) = events.next().await {
-            match app.state {
-                State::Normal => match key {
-                    Key::Char('q') => {
-                        quit_terminal(terminal);
-
-                        break 'outer;
-                    }
-                    Key::Char('i') => {
-                        app.state = State::Input;
-                    }
-                    _ => {}
-                },
-                State::Input => match key {
-                    Key::Ctrl('f') | Key::Right => {
-                        app.input_buffer.move_forward(1);
-                    }
-                    Key::Ctrl('b') | Key::Left => {
-                        app.input_buffer.move_backward(1);
-                    }
-                    Key::Ctrl('a') | Key::Home => {
-                        app.input_buffer.move_home();
-                    }
-                    Key::Ctrl('e') | Key::End => {
-                        app.input_buffer.move_end();
-                    }
-                    Key::Alt('f') => {
-                        app.input_buffer
-                            .move_to_next_word(At::AfterEnd, Word::Emacs, 1);
-                    }
-                    Key::Alt('b') => {
-                        app.input_buffer.move_to_prev_word(Word::Emacs, 1);
-                    }
-                    Key::Ctrl('t') => {
-                        app.input_buffer.transpose_chars();
-                    }
-                    Key::Alt('t') => {
-                        app.input_buffer.transpose_words(1);
-                    }
-                    Key::Ctrl('u') => {
-                        app.input_buffer.discard_line();
-                    }
-                    Key::Ctrl('k') => {
-                        app.input_buffer.kill_line();
-                    }
-                    Key::Ctrl('w') => {
-                        app.input_buffer.delete_prev_word(Word::Emacs, 1);
-                    }
-                    Key::Ctrl('d') => {
-                        app.input_buffer.delete(1);
-                    }
-                    Key::Backspace | Key::Delete => {
-                        app.input_buffer.backspace(1);
-                    }
-                    Key::Enter => {
-                        let input_message = &app.input_buffer.as_str();
-
-                        if !input_message.is_empty() {
-                            if let Ok((user, offset)) = parse_user_timezone(input_message) {
-                                app.storage.add(&user, offset);
-
-                                app.input_buffer.update("", 0);
-                            }
-                        }
-                    }
-                    Key::Char(c) => {
-                        app.input_buffer.insert(c, 1);
-                    }
-                    Key::Esc => {
-                        app.input_buffer.update("", 0);
-                        app.state = State::Normal;
-                    }
-                    _ => {}
-                },
+        let event = events.next().await;
+
+        if let Some(Event::Mouse(mouse)) = event {
+            if matches!(app.state, State::Plan | State::Help)
+                || (app.state == State::Normal && app.view.has_rows())
+            {
+                match mouse {
+                    Mouse::ScrollUp => app.move_selection(-1),
+                    Mouse::ScrollDown => app.move_selection(1),
+                    Mouse::Null => {}
+                }
+            }
+        }
+
+        if let Some(Event::Input(key)) = event {
+            let action = app.keybindings.action(app.state, key);
+
+            match (app.state, action) {
+                (_, Some(Action::Quit)) => {
+                    quit_terminal(terminal);
+
+                    break;
+                }
+                (State::Help, Some(Action::Cancel)) => {
+                    app.state = app.previous_state;
+                }
+                (_, Some(Action::Help)) => {
+                    app.previous_state = app.state;
+                    app.state = State::Help;
+                    app.help_state.select(Some(0));
+                }
+                (State::Normal, Some(action)) => handle_normal_action(&mut app, action),
+                (State::Delete, Some(action)) => handle_delete_action(&mut app, action),
+                (State::Plan, Some(action)) => handle_plan_action(&mut app, action),
+                (State::Help, Some(action)) => handle_help_action(&mut app, action),
+                (State::Input | State::Edit, _) => handle_input_key(&mut app, key, action),
+                (State::Filter, _) => handle_filter_key(&mut app, key, action),
+                (State::Convert, _) => handle_convert_key(&mut app, key, action),
+                (State::Help | State::Normal | State::Delete | State::Plan, _) => {}
             }
         }
     }
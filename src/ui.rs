@@ -1,56 +1,128 @@
 use std::string::ToString;
 
-use chrono::{Local, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, Local, Timelike, Utc};
+use rustyline::line_buffer::LineBuffer;
 use tui::{
     backend::Backend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     terminal::Frame,
-    widgets::{Block, Borders, Paragraph, Row, Table},
+    text::Line,
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Table,
+    },
 };
 
 use crate::{
     handlers::{
-        app::{App, State},
+        app::{App, State, Validity, View},
         config::CompleteConfig,
+        keybindings::Action,
     },
     utils::{
-        styles,
-        text::{align_columns, get_cursor_position, title_spans},
+        availability::{status_of, Availability},
+        coverage::{describe_gaps, hourly_coverage},
+        planner::{plan_headers, plan_row, DEFAULT_DAYS},
+        text::{
+            align_columns, describe_shift, get_cursor_position, table_row, title_spans,
+            TABLE_HEADERS,
+        },
+        timeline::{hour_cells, timeline_start, HOURS_BEFORE, TIMELINE_HOURS},
         timezones::parse_user_timezone,
     },
 };
+use unicode_width::UnicodeWidthStr;
+
+/// A rectangle of a certain size in the middle of another, shrunk to fit if needed.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
 
 pub fn draw_ui<T: Backend>(f: &mut Frame<T>, app: &mut App, config: &CompleteConfig) {
+    // The help popup is drawn on top of whatever state it was opened from.
+    let state = if app.state == State::Help {
+        app.previous_state
+    } else {
+        app.state
+    };
+
     let mut vertical_chunk_constraints = vec![Constraint::Min(1)];
 
-    if matches!(app.state, State::Input) {
+    if matches!(
+        state,
+        State::Input | State::Edit | State::Filter | State::Convert
+    ) {
         vertical_chunk_constraints.push(Constraint::Length(3));
     }
 
     let vertical_chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(config.frontend.margin)
-        .constraints(vertical_chunk_constraints.as_ref())
+        .constraints(vertical_chunk_constraints.as_slice())
         .split(f.size());
 
-    let time_rows = app
-        .storage
-        .get_all()
+    match (state, app.view) {
+        (State::Plan, _) => draw_plan(f, app, config, vertical_chunks[0]),
+        (_, View::Table) => draw_table(f, app, config, vertical_chunks[0]),
+        (_, View::Timeline) => draw_timeline(f, app, config, vertical_chunks[0]),
+        (_, View::Coverage) => draw_coverage(f, app, config, vertical_chunks[0]),
+    }
+
+    match state {
+        State::Input | State::Edit => {
+            draw_input(f, app, vertical_chunks[vertical_chunks.len() - 1]);
+        }
+        State::Filter => draw_filter(f, app, vertical_chunks[vertical_chunks.len() - 1]),
+        State::Convert => draw_convert(f, app, config, vertical_chunks[vertical_chunks.len() - 1]),
+        State::Delete => draw_delete_popup(f, app),
+        State::Normal | State::Help | State::Plan => {}
+    }
+
+    if app.state == State::Help {
+        draw_help_popup(f, app);
+    }
+}
+
+fn draw_table<T: Backend>(f: &mut Frame<T>, app: &mut App, config: &CompleteConfig, area: Rect) {
+    let live = Utc::now();
+    let now = app.viewed_instant_from(live);
+
+    let people = app.visible_people();
+
+    let time_rows = people
         .iter()
-        .map(|(k, v)| {
-            vec![
-                k.to_string(),
-                v.to_string(),
-                NaiveDateTime::from_timestamp_opt(Utc::now().timestamp() + v * 3600, 0)
-                    .unwrap()
-                    .format(config.frontend.time_format.as_str())
-                    .to_string(),
-            ]
+        .map(|p| {
+            let mut cells = table_row(p, now, &config.frontend);
+
+            if app.marked.contains(&p.id) {
+                cells[0] = format!("* {}", cells[0]);
+            }
+
+            cells
         })
         .collect::<Vec<Vec<String>>>();
 
-    let headers = vec!["User", "Offset", "Time"];
+    let row_styles = people
+        .iter()
+        .map(
+            |p| match status_of(p, now, &config.frontend.working_hours).availability {
+                Availability::Working => app.theme.working,
+                Availability::OffHours => app.theme.off_hours,
+                Availability::Sleeping => app.theme.sleeping,
+            },
+        )
+        .collect::<Vec<Style>>();
+
+    let headers = TABLE_HEADERS.to_vec();
 
     let (aligned_table, maximums) = align_columns(
         time_rows,
@@ -67,72 +139,534 @@ pub fn draw_ui<T: Backend>(f: &mut Frame<T>, app: &mut App, config: &CompleteCon
         .map(|l| Constraint::Length(*l))
         .collect::<Vec<Constraint>>();
 
+    let title_contents = people_title(app, config, live, people.len());
+
     let table = Table::new(
         aligned_table
             .iter()
-            .map(|cells| Row::new(cells.iter().map(ToString::to_string))),
+            .zip(row_styles)
+            .map(|(cells, style)| Row::new(cells.iter().map(ToString::to_string)).style(style)),
     )
-    .header(Row::new(headers).style(styles::COLUMN_TITLE))
+    .header(Row::new(headers).style(app.theme.column_title))
     .block(
         Block::default()
-            .style(styles::BORDER_NAME)
+            .style(app.theme.border)
             .borders(Borders::ALL)
             .title(title_spans(
-                vec![vec![
-                    "Local time",
-                    &Local::now()
-                        .format(config.frontend.time_format.as_str())
-                        .to_string(),
-                ]],
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                title_contents
+                    .iter()
+                    .map(|(name, value)| vec![*name, value.as_str()])
+                    .collect(),
+                app.theme.title,
             )),
     )
     .widths(table_constraints.as_ref())
-    .column_spacing(1);
+    .column_spacing(1)
+    .highlight_style(app.theme.selected)
+    .highlight_symbol("> ");
+
+    // Two borders and the header take up space that rows can't.
+    app.page_size = area.height.saturating_sub(3) as usize;
+
+    f.render_stateful_widget(table, area, &mut app.table_state);
+
+    draw_scrollbar(
+        f,
+        people.len(),
+        app.page_size,
+        app.table_state.selected(),
+        1,
+        area,
+    );
+}
+
+/// What the table or timeline of everyone is showing, for its title.
+fn people_title(
+    app: &App,
+    config: &CompleteConfig,
+    live: DateTime<Utc>,
+    rows: usize,
+) -> Vec<(&'static str, String)> {
+    let now = app.viewed_instant_from(live);
+
+    let mut local_time = now
+        .with_timezone(&Local)
+        .format(config.frontend.time_format.as_str())
+        .to_string();
+
+    if !app.is_live() {
+        local_time = format!("{local_time} ({})", describe_shift(now - live));
+    }
+
+    let mut title_contents = vec![
+        ("Local time", local_time),
+        ("Sort", app.sort.to_string()),
+        (
+            "Row",
+            format!(
+                "{} of {rows}",
+                app.table_state.selected().map_or(0, |i| i + 1)
+            ),
+        ),
+    ];
+
+    if !app.filter_buffer.is_empty() {
+        title_contents.push(("Filter", app.filter_buffer.to_string()));
+    }
+
+    if !app.marked.is_empty() {
+        title_contents.push(("Marked", app.marked.len().to_string()));
+    }
+
+    if app.view != View::Table {
+        title_contents.push(("View", app.view.to_string()));
+    }
+
+    title_contents
+}
+
+/// Shows how far down the rows of a bordered table the selection is, if they don't all fit.
+fn draw_scrollbar<T: Backend>(
+    f: &mut Frame<T>,
+    rows: usize,
+    page_size: usize,
+    selected: Option<usize>,
+    header_height: u16,
+    area: Rect,
+) {
+    if rows > page_size {
+        let mut scrollbar_state = ScrollbarState::default()
+            .content_length(rows as u16)
+            .viewport_content_length(page_size as u16)
+            .position(selected.unwrap_or(0) as u16);
 
-    f.render_widget(table, vertical_chunks[0]);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None),
+            // Placed on top of the right border, next to the rows.
+            Rect {
+                y: area.y + 1 + header_height,
+                height: area.height.saturating_sub(2 + header_height),
+                ..area
+            },
+            &mut scrollbar_state,
+        );
+    }
+}
+
+/// Everyone's clock over the hours around the time being viewed, shaded by whether they're
+/// working, off, or asleep. The hour being viewed is highlighted across every row.
+fn draw_timeline<T: Backend>(f: &mut Frame<T>, app: &mut App, config: &CompleteConfig, area: Rect) {
+    let live = Utc::now();
+    let now = app.viewed_instant_from(live);
+
+    let people = app.visible_people();
+
+    let name_width = people
+        .iter()
+        .map(|p| p.name.width())
+        .chain(std::iter::once("User".len()))
+        .max()
+        .unwrap_or(0) as u16;
+
+    // Each hour takes two characters and a space, next to the borders and the selection symbol.
+    let hours = (area.width.saturating_sub(name_width + 5) / 3).min(TIMELINE_HOURS);
+
+    let start = timeline_start(now);
+    let marker = |i: i64, style: Style| {
+        if i == HOURS_BEFORE {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style
+        }
+    };
+
+    let header = std::iter::once(Cell::from("User")).chain((0..i64::from(hours)).map(|i| {
+        // Labelled the same way as the cells, from the middle of the hour.
+        let hour = (start + Duration::hours(i) + Duration::minutes(30)).with_timezone(&Local);
+
+        Cell::from(hour.format("%H").to_string()).style(marker(i, Style::default()))
+    }));
+
+    let rows = people.iter().map(|p| {
+        let name = if app.marked.contains(&p.id) {
+            format!("* {}", p.name)
+        } else {
+            p.name.clone()
+        };
+
+        let cells = hour_cells(p, start, i64::from(hours), &config.frontend.working_hours)
+            .into_iter()
+            .zip(0..)
+            .map(|((label, availability), i)| {
+                let style = match availability {
+                    Availability::Working => app.theme.working,
+                    Availability::OffHours => app.theme.off_hours,
+                    Availability::Sleeping => app.theme.sleeping,
+                };
+
+                Cell::from(label).style(marker(i, style))
+            });
+
+        Row::new(std::iter::once(Cell::from(name)).chain(cells))
+    });
+
+    let widths = std::iter::once(Constraint::Length(name_width + 2))
+        .chain(std::iter::repeat(Constraint::Length(2)).take(usize::from(hours)))
+        .collect::<Vec<Constraint>>();
+
+    let title_contents = people_title(app, config, live, people.len());
+
+    let table = Table::new(rows)
+        .header(Row::new(header).style(app.theme.column_title))
+        .block(
+            Block::default()
+                .style(app.theme.border)
+                .borders(Borders::ALL)
+                .title(title_spans(
+                    title_contents
+                        .iter()
+                        .map(|(name, value)| vec![*name, value.as_str()])
+                        .collect(),
+                    app.theme.title,
+                )),
+        )
+        .widths(&widths)
+        .column_spacing(1)
+        .highlight_style(app.theme.selected)
+        .highlight_symbol("> ");
+
+    app.page_size = area.height.saturating_sub(3) as usize;
+
+    f.render_stateful_widget(table, area, &mut app.table_state);
+
+    draw_scrollbar(
+        f,
+        people.len(),
+        app.page_size,
+        app.table_state.selected(),
+        1,
+        area,
+    );
+}
+
+/// How many people are working during each hour of the UTC day being viewed, with the hours
+/// that nobody is around for picked out.
+fn draw_coverage<T: Backend>(f: &mut Frame<T>, app: &App, config: &CompleteConfig, area: Rect) {
+    let now = app.viewed_instant();
+
+    let people = app.visible_people();
+
+    let coverage = hourly_coverage(&people, &config.frontend.working_hours, now.date_naive());
+
+    let bars = coverage
+        .iter()
+        .zip(0..)
+        .map(|(count, hour)| {
+            let mut label_style = if *count == 0 {
+                app.theme.invalid
+            } else {
+                app.theme.text
+            };
+
+            if hour == now.hour() {
+                label_style = label_style.add_modifier(Modifier::REVERSED);
+            }
+
+            Bar::default()
+                .value(*count)
+                .label(Line::styled(format!("{hour:02}"), label_style))
+        })
+        .collect::<Vec<Bar>>();
+
+    let day = now.format("%a %b %d").to_string();
+    let gaps = describe_gaps(&coverage);
+    let total = people.len().to_string();
+
+    let title_contents = vec![
+        vec!["UTC day", day.as_str()],
+        vec!["People", total.as_str()],
+        vec!["Gaps", gaps.as_str()],
+        vec!["View", "coverage"],
+    ];
+
+    // The bars share what's left of the width after the borders, with a space between each.
+    let bar_width = (area.width.saturating_sub(2 + 23) / 24).max(1);
+
+    let chart = BarChart::default()
+        .block(
+            Block::default()
+                .style(app.theme.border)
+                .borders(Borders::ALL)
+                .title(title_spans(title_contents, app.theme.title)),
+        )
+        .data(BarGroup::default().bars(&bars))
+        .max(people.len().max(1) as u64)
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .bar_style(app.theme.working)
+        .value_style(app.theme.working.add_modifier(Modifier::REVERSED));
+
+    f.render_widget(chart, area);
+}
 
-    if matches!(app.state, State::Input) {
-        let text = &app.input_buffer;
+/// The times that the people being planned for can meet, in place of the table of everyone.
+fn draw_plan<T: Backend>(f: &mut Frame<T>, app: &mut App, config: &CompleteConfig, area: Rect) {
+    let people = app.planned_people();
+    let windows = app.plan_windows(&config.frontend.working_hours, app.viewed_instant());
 
-        if !text.is_empty() {
-            if let Ok((user, _)) = parse_user_timezone(text) {
-                if app.storage.contains(&user) {
-                    app.buffer_validity = styles::EXISTS;
+    // Scrubbing the time can change how many windows there are, so the selection is kept in range.
+    app.plan_length = windows.len();
+    app.keep_plan_selection();
+
+    let headers = plan_headers(&people);
+
+    let (aligned_table, maximums) = align_columns(
+        windows
+            .iter()
+            .enumerate()
+            .map(|(i, window)| plan_row(i + 1, window, &people))
+            .collect(),
+        headers.clone(),
+        headers.len(),
+        config.frontend.alignment.clone(),
+    );
+
+    let table_constraints = maximums
+        .iter()
+        .map(|l| Constraint::Length(*l))
+        .collect::<Vec<Constraint>>();
+
+    let names = people
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    let found = format!("{} in the next {DEFAULT_DAYS} days", windows.len());
+
+    let title_contents = vec![
+        vec!["Meeting", &names],
+        vec!["Windows", &found],
+        vec!["%", "away from the middle of their day"],
+    ];
+
+    let table = Table::new(
+        aligned_table
+            .iter()
+            .map(|cells| Row::new(cells.iter().map(ToString::to_string))),
+    )
+    .header(Row::new(headers).style(app.theme.column_title))
+    .block(
+        Block::default()
+            .style(app.theme.border)
+            .borders(Borders::ALL)
+            .title(title_spans(title_contents, app.theme.title)),
+    )
+    .widths(table_constraints.as_ref())
+    .column_spacing(1)
+    .highlight_style(app.theme.selected)
+    .highlight_symbol("> ");
+
+    app.page_size = area.height.saturating_sub(3) as usize;
+
+    f.render_stateful_widget(table, area, &mut app.plan_state);
+}
+
+fn draw_input<T: Backend>(f: &mut Frame<T>, app: &mut App, input_rect: Rect) {
+    let text = &app.input_buffer;
+
+    let editing = app.editing.and_then(|id| app.storage.get(id));
+
+    app.buffer_validity = if text.is_empty() {
+        Validity::Empty
+    } else {
+        match parse_user_timezone(text) {
+            Ok((user, _)) => {
+                if matches!(editing, Some(p) if p.name == user) || !app.storage.contains(&user) {
+                    Validity::Valid
+                } else if editing.is_some() {
+                    Validity::Taken
                 } else {
-                    app.buffer_validity = styles::VALID;
+                    Validity::Exists
                 }
-            } else {
-                app.buffer_validity = styles::INVALID;
             }
+            Err(err) => Validity::Invalid(err.to_string()),
         }
+    };
 
-        let cursor_pos = get_cursor_position(text);
+    let style = match app.buffer_validity {
+        Validity::Empty => app.theme.column_title,
+        Validity::Valid => app.theme.valid,
+        Validity::Exists => app.theme.exists,
+        Validity::Taken | Validity::Invalid(_) => app.theme.invalid,
+    };
 
-        let input_rect = vertical_chunks[vertical_chunk_constraints.len() - 1];
+    let name = editing.map_or_else(|| "Input".to_string(), |p| format!("Editing {}", p.name));
 
-        f.set_cursor(
-            (input_rect.x + cursor_pos as u16 + 1)
-                .min(input_rect.x + input_rect.width.saturating_sub(2)),
-            input_rect.y + 1,
-        );
+    let title = if app.buffer_validity == Validity::Empty {
+        Line::from(format!("[ {name} ]"))
+    } else {
+        title_spans(vec![vec![&name, app.buffer_validity.marker()]], style)
+    };
 
-        let paragraph = Paragraph::new(text.as_str())
-            .style(Style::default().fg(Color::Yellow))
-            .block(
-                Block::default()
-                    .style(app.buffer_validity)
-                    .borders(Borders::ALL)
-                    .title("[ Input ]"),
-            )
-            .scroll((
-                0,
-                ((cursor_pos + 3) as u16).saturating_sub(input_rect.width),
-            ));
-
-        f.render_widget(
-            paragraph,
-            vertical_chunks[vertical_chunk_constraints.len() - 1],
-        );
-    }
+    let block = Block::default()
+        .style(style)
+        .borders(Borders::ALL)
+        .title(title);
+
+    draw_line(f, text, app.theme.text, block, input_rect);
+}
+
+fn draw_filter<T: Backend>(f: &mut Frame<T>, app: &App, filter_rect: Rect) {
+    let block = Block::default()
+        .style(app.theme.column_title)
+        .borders(Borders::ALL)
+        .title("[ Filter ]");
+
+    draw_line(f, &app.filter_buffer, app.theme.text, block, filter_rect);
+}
+
+fn draw_convert<T: Backend>(f: &mut Frame<T>, app: &App, config: &CompleteConfig, rect: Rect) {
+    let (style, title) = if app.convert_buffer.is_empty() {
+        (
+            app.theme.column_title,
+            Line::from("[ Convert: a time such as Fri 15:00, 2023-10-20T09:00, or 15:00 @Alice ]"),
+        )
+    } else {
+        match app.conversion() {
+            Ok(instant) => {
+                let local = instant
+                    .with_timezone(&Local)
+                    .format(&config.frontend.time_format)
+                    .to_string();
+
+                (
+                    app.theme.valid,
+                    title_spans(vec![vec!["Convert", &local]], app.theme.valid),
+                )
+            }
+            Err(err) => (
+                app.theme.invalid,
+                title_spans(vec![vec!["Convert", &err.to_string()]], app.theme.invalid),
+            ),
+        }
+    };
+
+    let block = Block::default()
+        .style(style)
+        .borders(Borders::ALL)
+        .title(title);
+
+    draw_line(f, &app.convert_buffer, app.theme.text, block, rect);
+}
+
+/// A single line of editable text, with the cursor placed inside of it.
+fn draw_line<T: Backend>(
+    f: &mut Frame<T>,
+    text: &LineBuffer,
+    style: Style,
+    block: Block,
+    rect: Rect,
+) {
+    let cursor_pos = get_cursor_position(text);
+
+    f.set_cursor(
+        (rect.x + cursor_pos as u16 + 1).min(rect.x + rect.width.saturating_sub(2)),
+        rect.y + 1,
+    );
+
+    let paragraph = Paragraph::new(text.as_str())
+        .style(style)
+        .block(block)
+        .scroll((0, ((cursor_pos + 3) as u16).saturating_sub(rect.width)));
+
+    f.render_widget(paragraph, rect);
+}
+
+fn draw_delete_popup<T: Backend>(f: &mut Frame<T>, app: &App) {
+    let Some(person) = app.selected_person() else {
+        return;
+    };
+
+    let key_of = |action| {
+        app.keybindings
+            .delete
+            .get(&action)
+            .and_then(|keys| keys.first())
+            .map_or_else(String::new, ToString::to_string)
+    };
+
+    let text = format!(
+        "Delete {}? [{}/{}]",
+        person.name,
+        key_of(Action::Submit),
+        key_of(Action::Cancel)
+    );
+
+    let popup_rect = centered_rect(text.width() as u16 + 4, 3, f.size());
+
+    let paragraph = Paragraph::new(text).alignment(Alignment::Center).block(
+        Block::default()
+            .style(app.theme.invalid)
+            .borders(Borders::ALL)
+            .title("[ Delete ]"),
+    );
+
+    f.render_widget(Clear, popup_rect);
+    f.render_widget(paragraph, popup_rect);
+}
+
+fn draw_help_popup<T: Backend>(f: &mut Frame<T>, app: &mut App) {
+    let help = app.keybindings.help(app.previous_state);
+
+    let keys_width = help.iter().map(|(keys, _)| keys.width()).max().unwrap_or(0) as u16;
+    let description_width = help
+        .iter()
+        .map(|(_, description)| description.width())
+        .max()
+        .unwrap_or(0) as u16;
+
+    // Room is left for the highlight symbol, so that the columns don't shift while scrolling.
+    let popup_rect = centered_rect(
+        keys_width + description_width + 7,
+        help.len() as u16 + 2,
+        f.size(),
+    );
+
+    // Short terminals can't fit every binding, so the list scrolls with the selection.
+    app.help_length = help.len();
+    app.page_size = popup_rect.height.saturating_sub(2) as usize;
+
+    let widths = [
+        Constraint::Length(keys_width),
+        Constraint::Length(description_width),
+    ];
+
+    let table = Table::new(
+        help.into_iter()
+            .map(|(keys, description)| Row::new(vec![keys, description.to_string()])),
+    )
+    .block(
+        Block::default()
+            .style(app.theme.border)
+            .borders(Borders::ALL)
+            .title(format!("[ Help: {} ]", app.previous_state)),
+    )
+    .widths(&widths)
+    .column_spacing(2)
+    .highlight_style(app.theme.selected)
+    .highlight_symbol("> ");
+
+    f.render_widget(Clear, popup_rect);
+    f.render_stateful_widget(table, popup_rect, &mut app.help_state);
+
+    draw_scrollbar(
+        f,
+        app.help_length,
+        app.page_size,
+        app.help_state.selected(),
+        0,
+        popup_rect,
+    );
 }
@@ -0,0 +1,440 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use color_eyre::eyre::{anyhow, bail, Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{handlers::storage::Person, utils::timezones::Timezone};
+
+/// How long people are assumed to sleep for each day, when not working.
+const SLEEP_HOURS: i64 = 8;
+/// How long before work starts people are assumed to wake up, so `09:00` means asleep until `07:00`.
+const WAKE_HOURS_BEFORE_WORK: i64 = 2;
+
+/// When someone usually works on their own clock, such as `Mon-Fri 09:00-17:00`.
+/// Hours that end before they start go on past midnight, into the next day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkingHours {
+    /// The days that work starts on, from Monday to Sunday.
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Whether someone is around at a specific instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    Working,
+    OffHours,
+    Sleeping,
+}
+
+/// Someone's availability, and how long it is until it changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub availability: Availability,
+    /// How long until work starts or ends, if they ever work.
+    pub change_in: Option<Duration>,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        }
+    }
+}
+
+impl WorkingHours {
+    /// The stretch of work that starts on a day, if it's a workday.
+    fn shift_on(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        if !self.days.contains(&date.weekday()) {
+            return None;
+        }
+
+        let start = date.and_time(self.start);
+        let end = date.and_time(self.end);
+
+        if end > start {
+            Some((start, end))
+        } else {
+            Some((start, end + Duration::days(1)))
+        }
+    }
+
+    /// Every stretch of work that starts within a day of the local time, or in the week after.
+    fn shifts_around(&self, local: NaiveDateTime) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        (-1..=7)
+            .filter_map(|days| self.shift_on(local.date() + Duration::days(days)))
+            .collect()
+    }
+
+    /// Every stretch of work that overlaps with a period of time, in the order they start.
+    /// Shifts are kept whole, and those that start or end on a skipped clock time are left out.
+    pub fn shifts_between(
+        &self,
+        timezone: &Timezone,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let first = timezone.local_time_at(from).date() - Duration::days(1);
+        let last = timezone.local_time_at(to).date();
+
+        first
+            .iter_days()
+            .take_while(|date| *date <= last)
+            .filter_map(|date| self.shift_on(date))
+            .filter_map(|(start, end)| {
+                Some((
+                    timezone.instant_from_local(start).ok()?,
+                    timezone.instant_from_local(end).ok()?,
+                ))
+            })
+            .filter(|(start, end)| *end > from && *start < to)
+            .collect()
+    }
+
+    /// The status of someone with these hours, when their clock shows a specific time.
+    pub fn status_at(&self, local: NaiveDateTime) -> Status {
+        let shifts = self.shifts_around(local);
+
+        if let Some((_, end)) = shifts
+            .iter()
+            .find(|(start, end)| *start <= local && local < *end)
+        {
+            return Status {
+                availability: Availability::Working,
+                change_in: Some(*end - local),
+            };
+        }
+
+        // Sleep follows the start of work, every day, so that night shifts sleep during the day.
+        let wake = self.start - Duration::hours(WAKE_HOURS_BEFORE_WORK);
+        let since_waking = (local.time() - wake).num_minutes().rem_euclid(24 * 60);
+
+        let availability = if since_waking < (24 - SLEEP_HOURS) * 60 {
+            Availability::OffHours
+        } else {
+            Availability::Sleeping
+        };
+
+        Status {
+            availability,
+            change_in: shifts
+                .iter()
+                .map(|(start, _)| *start)
+                .filter(|start| *start > local)
+                .min()
+                .map(|start| start - local),
+        }
+    }
+}
+
+impl fmt::Display for WorkingHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut runs: Vec<(Weekday, Weekday)> = vec![];
+
+        for day in &self.days {
+            match runs.last_mut() {
+                Some((_, last)) if last.succ() == *day && *day != Weekday::Mon => *last = *day,
+                _ => runs.push((*day, *day)),
+            }
+        }
+
+        let days = runs
+            .iter()
+            .map(|(first, last)| {
+                if first == last {
+                    format!("{first}")
+                } else {
+                    format!("{first}-{last}")
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        write!(
+            f,
+            "{days} {}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+impl FromStr for WorkingHours {
+    type Err = Error;
+
+    /// Days are separated by commas, and can be ranges such as `Sun-Thu`.
+    /// The times come after the last space, so that days can have spaces after their commas.
+    fn from_str(s: &str) -> Result<Self> {
+        let (days_text, times_text) = s
+            .trim()
+            .rsplit_once(' ')
+            .ok_or_else(|| anyhow!("Working hours {s} should look like Mon-Fri 09:00-17:00."))?;
+
+        let parse_day = |text: &str| {
+            text.trim()
+                .parse::<Weekday>()
+                .map_err(|_| anyhow!("Unknown day {text}."))
+        };
+
+        let mut days = vec![];
+
+        for part in days_text.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+                None => (parse_day(part)?, parse_day(part)?),
+            };
+
+            let mut day = first;
+
+            days.push(day);
+
+            while day != last {
+                day = day.succ();
+                days.push(day);
+            }
+        }
+
+        days.sort_by_key(Weekday::num_days_from_monday);
+        days.dedup();
+
+        let (start, end) = times_text
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Working hours {s} should look like Mon-Fri 09:00-17:00."))?;
+
+        let parse_time = |text: &str| {
+            NaiveTime::parse_from_str(text.trim(), "%H:%M")
+                .map_err(|_| anyhow!("Unable to read the time {text}, use a format such as 09:00."))
+        };
+
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+
+        if start == end {
+            bail!("Working hours can't start and end at the same time.");
+        }
+
+        Ok(Self { days, start, end })
+    }
+}
+
+impl Serialize for WorkingHours {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for WorkingHours {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for Availability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Working => write!(f, "working"),
+            Self::OffHours => write!(f, "off-hours"),
+            Self::Sleeping => write!(f, "sleeping"),
+        }
+    }
+}
+
+/// The status of someone at a specific instant, going by their own hours or the default ones.
+pub fn status_of(person: &Person, instant: DateTime<Utc>, default_hours: &WorkingHours) -> Status {
+    person
+        .hours
+        .as_ref()
+        .unwrap_or(default_hours)
+        .status_at(person.timezone.local_time_at(instant))
+}
+
+/// A rough amount of time, such as `40m`, `2h 30m`, or `1d 3h`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{m}m"),
+        (0, h, 0) => format!("{h}h"),
+        (0, h, m) => format!("{h}h {m}m"),
+        (d, 0, _) => format!("{d}d"),
+        (d, h, _) => format!("{d}d {h}h"),
+    }
+}
+
+impl Status {
+    /// When work starts or ends, such as `starts in 2h`.
+    pub fn describe_change(&self) -> String {
+        let Some(change_in) = self.change_in else {
+            return String::new();
+        };
+
+        let verb = if self.availability == Availability::Working {
+            "ends"
+        } else {
+            "starts"
+        };
+
+        format!("{verb} in {}", format_duration(change_in))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // The 16th of October 2023 is a Monday.
+        NaiveDate::from_ymd_opt(2023, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_working_hours_round_trip() {
+        for text in [
+            "Mon-Fri 09:00-17:00",
+            "Mon,Wed,Fri 08:30-12:00",
+            "Mon-Thu,Sun 22:00-06:00",
+        ] {
+            assert_eq!(text.parse::<WorkingHours>().unwrap().to_string(), text);
+        }
+
+        assert_eq!(
+            "sun-thu 9:00-17:00"
+                .parse::<WorkingHours>()
+                .unwrap()
+                .to_string(),
+            "Mon-Thu,Sun 09:00-17:00"
+        );
+
+        assert_eq!(
+            "Mon, Wed - Fri 09:00-17:00"
+                .parse::<WorkingHours>()
+                .unwrap()
+                .to_string(),
+            "Mon,Wed-Fri 09:00-17:00"
+        );
+    }
+
+    #[test]
+    fn test_working_hours_errors() {
+        assert_eq!(
+            "Mon-Fri".parse::<WorkingHours>().unwrap_err().to_string(),
+            "Working hours Mon-Fri should look like Mon-Fri 09:00-17:00."
+        );
+        assert_eq!(
+            "Mon-Fry 09:00-17:00"
+                .parse::<WorkingHours>()
+                .unwrap_err()
+                .to_string(),
+            "Unknown day Fry."
+        );
+        assert!("Mon 09:00-09:00".parse::<WorkingHours>().is_err());
+    }
+
+    #[test]
+    fn test_status_during_the_week() {
+        let hours = WorkingHours::default();
+
+        let working = hours.status_at(at(16, 16, 20));
+        assert_eq!(working.availability, Availability::Working);
+        assert_eq!(working.describe_change(), "ends in 40m");
+
+        let evening = hours.status_at(at(16, 18, 0));
+        assert_eq!(evening.availability, Availability::OffHours);
+        assert_eq!(evening.describe_change(), "starts in 15h");
+
+        let night = hours.status_at(at(17, 6, 30));
+        assert_eq!(night.availability, Availability::Sleeping);
+        assert_eq!(night.describe_change(), "starts in 2h 30m");
+
+        let weekend = hours.status_at(at(21, 12, 0));
+        assert_eq!(weekend.availability, Availability::OffHours);
+        assert_eq!(weekend.describe_change(), "starts in 1d 21h");
+    }
+
+    #[test]
+    fn test_status_past_midnight() {
+        let hours = "Fri 22:00-06:00".parse::<WorkingHours>().unwrap();
+
+        let status = hours.status_at(at(21, 2, 0));
+
+        assert_eq!(status.availability, Availability::Working);
+        assert_eq!(status.describe_change(), "ends in 4h");
+    }
+
+    #[test]
+    fn test_sleep_follows_the_start_of_work() {
+        let hours = "Mon-Fri 22:00-06:00".parse::<WorkingHours>().unwrap();
+
+        assert_eq!(
+            hours.status_at(at(17, 1, 0)).availability,
+            Availability::Working
+        );
+        assert_eq!(
+            hours.status_at(at(17, 7, 0)).availability,
+            Availability::OffHours
+        );
+        assert_eq!(
+            hours.status_at(at(17, 13, 0)).availability,
+            Availability::Sleeping
+        );
+        assert_eq!(
+            hours.status_at(at(17, 20, 0)).availability,
+            Availability::OffHours
+        );
+    }
+
+    #[test]
+    fn test_shifts_between_follow_daylight_saving() {
+        let hours = WorkingHours::default();
+        let berlin = Timezone::Named(chrono_tz::Europe::Berlin);
+
+        // Berlin leaves summer time on the 29th of October 2023, a Sunday.
+        let from = Utc.with_ymd_and_hms(2023, 10, 27, 12, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2023, 10, 31, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            hours.shifts_between(&berlin, from, to),
+            vec![
+                (
+                    Utc.with_ymd_and_hms(2023, 10, 27, 7, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2023, 10, 27, 15, 0, 0).unwrap()
+                ),
+                (
+                    Utc.with_ymd_and_hms(2023, 10, 30, 8, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2023, 10, 30, 16, 0, 0).unwrap()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_status_without_workdays() {
+        let hours = WorkingHours {
+            days: vec![],
+            ..WorkingHours::default()
+        };
+
+        let status = hours.status_at(at(16, 12, 0));
+
+        assert_eq!(status.availability, Availability::OffHours);
+        assert_eq!(status.describe_change(), "");
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::{
+    handlers::storage::Person,
+    utils::availability::{status_of, Availability, WorkingHours},
+};
+
+/// How many people are working during each hour of a UTC day, going by the middle of the hour.
+pub fn hourly_coverage(
+    people: &[Person],
+    default_hours: &WorkingHours,
+    day: NaiveDate,
+) -> Vec<u64> {
+    let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+    (0..24)
+        .map(|hour| {
+            let middle = start + Duration::hours(hour) + Duration::minutes(30);
+
+            people
+                .iter()
+                .filter(|p| {
+                    status_of(p, middle, default_hours).availability == Availability::Working
+                })
+                .count() as u64
+        })
+        .collect()
+}
+
+/// The stretches of hours that nobody is working during, such as `10:00-11:00, 22:00-02:00`.
+/// Stretches going past midnight are joined with the start of the day.
+pub fn describe_gaps(coverage: &[u64]) -> String {
+    if coverage.iter().all(|c| *c == 0) {
+        return "all day".to_string();
+    }
+
+    // Start right after a covered hour, so that no gap is split in two by the end of the day.
+    let first = coverage.iter().position(|c| *c != 0).unwrap_or(0);
+    let hours = coverage.len();
+
+    let mut gaps = vec![];
+    let mut gap_start = None;
+
+    for i in first..=first + hours {
+        let hour = i % hours;
+
+        match (coverage[hour] == 0 && i < first + hours, gap_start) {
+            (true, None) => gap_start = Some(hour),
+            (false, Some(start)) => {
+                gaps.push(format!("{start:02}:00-{hour:02}:00"));
+                gap_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if gaps.is_empty() {
+        "none".to_string()
+    } else {
+        gaps.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::timezones::Timezone;
+
+    #[test]
+    fn test_hourly_coverage_counts_working_people() {
+        let people = vec![
+            Person::for_test("Alice", Timezone::Offset(0)),
+            Person::for_test("Bob", Timezone::Offset(120)),
+            Person::for_test("Carol", Timezone::Offset(-300)),
+        ];
+
+        // A Monday, when they work 07:00-15:00, 09:00-17:00, and 14:00-22:00 in UTC.
+        let day = NaiveDate::from_ymd_opt(2023, 10, 16).unwrap();
+
+        let coverage = hourly_coverage(&people, &WorkingHours::default(), day);
+
+        assert_eq!(coverage.len(), 24);
+        assert_eq!(coverage[6], 0);
+        assert_eq!(coverage[7], 1);
+        assert_eq!(coverage[9], 2);
+        assert_eq!(coverage[14], 3);
+        assert_eq!(coverage[15], 2);
+        assert_eq!(coverage[17], 1);
+        assert_eq!(coverage[21], 1);
+        assert_eq!(coverage[22], 0);
+    }
+
+    #[test]
+    fn test_describe_gaps() {
+        let mut coverage = vec![0; 24];
+
+        assert_eq!(describe_gaps(&coverage), "all day");
+
+        coverage[2..10].fill(1);
+        coverage[11..22].fill(2);
+
+        assert_eq!(describe_gaps(&coverage), "10:00-11:00, 22:00-02:00");
+
+        assert_eq!(describe_gaps(&[1; 24]), "none");
+    }
+}
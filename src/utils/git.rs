@@ -0,0 +1,235 @@
+use std::{collections::HashMap, path::Path, process::Command};
+
+use chrono::DateTime;
+use color_eyre::eyre::{bail, Result, WrapErr};
+
+use crate::utils::timezones::Timezone;
+
+/// Separates the fields of a commit, as nobody puts it in their name.
+const SEPARATOR: char = '\u{1f}';
+
+/// Which of an author's offsets is picked, when they've committed from several.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pick {
+    /// The offset most of their commits were made at.
+    #[default]
+    Common,
+    /// The offset of their latest commit.
+    Recent,
+}
+
+/// A single commit, newest first in the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub name: String,
+    pub email: String,
+    /// Minutes away from UTC that the author's clock was at.
+    pub offset: i64,
+}
+
+/// Someone that could be tracked, going by their commits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    /// The name of their latest commit.
+    pub name: String,
+    pub email: String,
+    pub timezone: Timezone,
+    pub commits: usize,
+    /// How many of the commits were made at the proposed offset.
+    pub matching: usize,
+}
+
+/// The authors and author dates of a repository, newest first.
+pub fn read_log(repository: &Path, since: Option<&str>) -> Result<String> {
+    let mut command = Command::new("git");
+
+    command
+        .arg("-C")
+        .arg(repository)
+        .arg("log")
+        .arg(format!("--format=%aN{SEPARATOR}%aE{SEPARATOR}%aI"));
+
+    if let Some(since) = since {
+        command.arg(format!("--since={since}"));
+    }
+
+    let output = command.output().wrap_err("Unable to run git.")?;
+
+    if !output.status.success() {
+        bail!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Reads the commits from the output of `read_log`, leaving out lines it doesn't understand.
+pub fn parse_log(text: &str) -> Vec<Commit> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(SEPARATOR);
+
+            let name = fields.next()?;
+            let email = fields.next()?;
+            let date = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+
+            Some(Commit {
+                // Git drops spaces around names when committing, but not when given a log by hand.
+                name: name.trim().to_string(),
+                email: email.to_lowercase(),
+                offset: i64::from(date.offset().local_minus_utc()) / 60,
+            })
+        })
+        .collect()
+}
+
+/// Groups commits by the email of their author, proposing an offset for each of them.
+/// Authors are in the order of their latest commit. Those whose offset couldn't be anyone's
+/// are left out, and returned separately with their email and why.
+pub fn propose(commits: &[Commit], pick: Pick) -> (Vec<Proposal>, Vec<(String, String)>) {
+    let mut authors: Vec<(&Commit, Vec<i64>)> = vec![];
+    let mut positions = HashMap::new();
+
+    for commit in commits {
+        let position = *positions.entry(&commit.email).or_insert_with(|| {
+            authors.push((commit, vec![]));
+
+            authors.len() - 1
+        });
+
+        authors[position].1.push(commit.offset);
+    }
+
+    let mut proposals = vec![];
+    let mut rejected = vec![];
+
+    for (latest, offsets) in authors {
+        let offset = match pick {
+            Pick::Recent => offsets[0],
+            // Ties go to whichever offset was used most recently.
+            Pick::Common => *offsets
+                .iter()
+                .rev()
+                .max_by_key(|o| offsets.iter().filter(|other| other == o).count())
+                .unwrap(),
+        };
+
+        match Timezone::from_offset(offset) {
+            Ok(timezone) => proposals.push(Proposal {
+                name: latest.name.clone(),
+                email: latest.email.clone(),
+                timezone,
+                commits: offsets.len(),
+                matching: offsets.iter().filter(|o| **o == offset).count(),
+            }),
+            Err(err) => rejected.push((latest.email.clone(), err.to_string())),
+        }
+    }
+
+    (proposals, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(lines: &[(&str, &str, &str)]) -> String {
+        lines
+            .iter()
+            .map(|(name, email, date)| format!("{name}{SEPARATOR}{email}{SEPARATOR}{date}\n"))
+            .collect::<Vec<String>>()
+            .concat()
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let text = log(&[
+            (" Alice ", "Alice@Example.com", "2023-10-20T15:00:00+05:30"),
+            ("Bob", "bob@example.com", "not a date"),
+            ("Bob", "bob@example.com", "2023-10-19T09:00:00-04:00"),
+        ]);
+
+        assert_eq!(
+            parse_log(&text),
+            vec![
+                Commit {
+                    name: "Alice".to_string(),
+                    email: "alice@example.com".to_string(),
+                    offset: 330,
+                },
+                Commit {
+                    name: "Bob".to_string(),
+                    email: "bob@example.com".to_string(),
+                    offset: -240,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_propose_groups_by_email() {
+        let text = log(&[
+            (
+                "Alice Smith",
+                "alice@example.com",
+                "2023-10-20T15:00:00+01:00",
+            ),
+            ("Bob", "bob@example.com", "2023-10-20T12:00:00-04:00"),
+            ("Alice", "alice@example.com", "2023-10-19T15:00:00+02:00"),
+            ("Alice", "alice@example.com", "2023-10-18T15:00:00+02:00"),
+            ("Bob", "bob@example.com", "2023-10-17T12:00:00-05:00"),
+        ]);
+
+        let commits = parse_log(&text);
+
+        let (common, rejected) = propose(&commits, Pick::Common);
+
+        assert!(rejected.is_empty());
+
+        assert_eq!(
+            common,
+            vec![
+                Proposal {
+                    name: "Alice Smith".to_string(),
+                    email: "alice@example.com".to_string(),
+                    timezone: Timezone::Offset(120),
+                    commits: 3,
+                    matching: 2,
+                },
+                Proposal {
+                    name: "Bob".to_string(),
+                    email: "bob@example.com".to_string(),
+                    timezone: Timezone::Offset(-240),
+                    commits: 2,
+                    matching: 1,
+                },
+            ]
+        );
+
+        let (recent, _) = propose(&commits, Pick::Recent);
+
+        assert_eq!(recent[0].timezone, Timezone::Offset(60));
+    }
+
+    #[test]
+    fn test_propose_leaves_out_impossible_offsets() {
+        let text = log(&[
+            ("Alice", "alice@example.com", "2023-10-20T15:00:00+02:00"),
+            ("Bob", "bob@example.com", "2023-10-20T15:00:00+23:00"),
+        ]);
+
+        let (proposals, rejected) = propose(&parse_log(&text), Pick::Common);
+
+        assert_eq!(proposals.len(), 1);
+        assert_eq!(proposals[0].name, "Alice");
+        assert_eq!(
+            rejected,
+            vec![(
+                "bob@example.com".to_string(),
+                "Offset +23:00 is out of range, it must be between -12:00 and +14:00.".to_string()
+            )]
+        );
+    }
+}
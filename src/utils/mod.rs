@@ -1,4 +1,12 @@
+pub mod availability;
+pub mod coverage;
+pub mod git;
 pub mod pathing;
+pub mod planner;
+pub mod search;
+pub mod sorting;
 pub mod styles;
 pub mod text;
+pub mod timeline;
 pub mod timezones;
+pub mod vcard;
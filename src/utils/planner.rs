@@ -0,0 +1,284 @@
+use chrono::{DateTime, Duration, Local, NaiveDateTime, Utc};
+
+use crate::{
+    handlers::storage::Person,
+    utils::availability::{format_duration, WorkingHours},
+};
+
+/// How many days ahead meetings are planned, unless asked otherwise.
+pub const DEFAULT_DAYS: i64 = 7;
+/// How many minutes a meeting needs at least, unless asked otherwise.
+pub const DEFAULT_MINUTES: i64 = 30;
+
+/// A stretch of time that everyone is working during.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// How far the middle of the window is from the middle of each participant's working day,
+    /// from 0 at the very middle to 100 at the start or end of it.
+    pub inconvenience: Vec<i64>,
+}
+
+impl Window {
+    /// The inconvenience of whoever the window suits the least.
+    pub fn worst(&self) -> i64 {
+        self.inconvenience.iter().copied().max().unwrap_or(0)
+    }
+}
+
+/// The parts that two lists of stretches of time have in common, in order.
+fn intersect(
+    a: &[(DateTime<Utc>, DateTime<Utc>)],
+    b: &[(DateTime<Utc>, DateTime<Utc>)],
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut overlaps = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+
+        if start < end {
+            overlaps.push((start, end));
+        }
+
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    overlaps
+}
+
+/// Every window over the next days that's long enough for a meeting, least inconvenient first.
+/// Windows are ranked by whoever they suit the least, then by everyone together, then by time.
+pub fn find_windows(
+    people: &[Person],
+    default_hours: &WorkingHours,
+    from: DateTime<Utc>,
+    days: i64,
+    minimum: Duration,
+) -> Vec<Window> {
+    let to = from + Duration::days(days);
+
+    let shifts = people
+        .iter()
+        .map(|p| {
+            p.hours
+                .as_ref()
+                .unwrap_or(default_hours)
+                .shifts_between(&p.timezone, from, to)
+        })
+        .collect::<Vec<Vec<(DateTime<Utc>, DateTime<Utc>)>>>();
+
+    let Some((first, rest)) = shifts.split_first() else {
+        return vec![];
+    };
+
+    let overlaps = rest
+        .iter()
+        .fold(first.clone(), |overlaps, other| intersect(&overlaps, other));
+
+    let mut windows = overlaps
+        .into_iter()
+        .map(|(start, end)| (start.max(from), end.min(to)))
+        .filter(|(start, end)| *end - *start >= minimum)
+        .map(|(start, end)| {
+            let middle = start + (end - start) / 2;
+
+            let inconvenience = shifts
+                .iter()
+                .map(|own| {
+                    own.iter()
+                        .find(|(s, e)| *s <= middle && middle < *e)
+                        .map_or(100, |(s, e)| {
+                            let half = (*e - *s).num_seconds() / 2;
+                            let middle_of_day = *s + Duration::seconds(half);
+
+                            (middle - middle_of_day).num_seconds().abs() * 100 / half.max(1)
+                        })
+                })
+                .collect();
+
+            Window {
+                start,
+                end,
+                inconvenience,
+            }
+        })
+        .collect::<Vec<Window>>();
+
+    windows.sort_by_key(|w| (w.worst(), w.inconvenience.iter().sum::<i64>(), w.start));
+
+    windows
+}
+
+/// Two times on the same clock, such as `Mon 09:00-11:30` or `Mon 22:00-Tue 01:00`.
+pub fn format_range(start: NaiveDateTime, end: NaiveDateTime) -> String {
+    if start.date() == end.date() {
+        format!("{}-{}", start.format("%a %H:%M"), end.format("%H:%M"))
+    } else {
+        format!("{}-{}", start.format("%a %H:%M"), end.format("%a %H:%M"))
+    }
+}
+
+/// The columns of the table of windows, with a column for each participant.
+pub fn plan_headers(people: &[Person]) -> Vec<String> {
+    ["#", "Date", "Local", "Length"]
+        .iter()
+        .map(ToString::to_string)
+        .chain(people.iter().map(|p| p.name.clone()))
+        .collect()
+}
+
+/// A single row of the table of windows, in local time followed by everyone's own time.
+pub fn plan_row(rank: usize, window: &Window, people: &[Person]) -> Vec<String> {
+    let local_start = window.start.with_timezone(&Local).naive_local();
+    let local_end = window.end.with_timezone(&Local).naive_local();
+
+    let mut cells = vec![
+        rank.to_string(),
+        local_start.format("%b %d").to_string(),
+        format_range(local_start, local_end),
+        format_duration(window.end - window.start),
+    ];
+
+    for (person, inconvenience) in people.iter().zip(&window.inconvenience) {
+        let range = format_range(
+            person.timezone.local_time_at(window.start),
+            person.timezone.local_time_at(window.end),
+        );
+
+        cells.push(format!("{range} ({inconvenience}%)"));
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::utils::timezones::Timezone;
+
+    fn person(name: &str, timezone: Timezone, hours: Option<&str>) -> Person {
+        Person {
+            hours: hours.map(|h| h.parse().unwrap()),
+            ..Person::for_test(name, timezone)
+        }
+    }
+
+    fn utc(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 10, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_windows_are_the_overlap_of_everyone() {
+        let people = vec![
+            person("Alice", Timezone::Offset(120), None),
+            person("Bob", Timezone::Offset(-240), None),
+        ];
+
+        // Monday the 16th of October 2023, at midnight.
+        let windows = find_windows(
+            &people,
+            &WorkingHours::default(),
+            utc(16, 0),
+            2,
+            Duration::minutes(30),
+        );
+
+        // Alice works 07:00-15:00 and Bob works 13:00-21:00, in UTC.
+        assert_eq!(
+            windows,
+            vec![
+                Window {
+                    start: utc(16, 13),
+                    end: utc(16, 15),
+                    inconvenience: vec![75, 75],
+                },
+                Window {
+                    start: utc(17, 13),
+                    end: utc(17, 15),
+                    inconvenience: vec![75, 75],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windows_are_ranked_by_inconvenience() {
+        let people = vec![
+            person("Alice", Timezone::Offset(0), None),
+            person("Bob", Timezone::Offset(0), Some("Mon-Fri 10:00-18:00")),
+        ];
+
+        // Monday at noon, so that the first and last windows are cut short.
+        let windows = find_windows(
+            &people,
+            &WorkingHours::default(),
+            utc(16, 12),
+            2,
+            Duration::minutes(30),
+        );
+
+        assert_eq!(
+            windows
+                .iter()
+                .map(|w| (w.start, w.end, w.inconvenience.clone()))
+                .collect::<Vec<(DateTime<Utc>, DateTime<Utc>, Vec<i64>)>>(),
+            vec![
+                (utc(17, 10), utc(17, 17), vec![12, 12]),
+                (utc(16, 12), utc(16, 17), vec![37, 12]),
+                (utc(18, 10), utc(18, 12), vec![50, 75]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_short_and_past_windows_are_left_out() {
+        let people = vec![
+            person("Alice", Timezone::Offset(0), None),
+            person("Bob", Timezone::Offset(-465), None),
+        ];
+
+        let windows = find_windows(
+            &people,
+            &WorkingHours::default(),
+            utc(16, 0),
+            1,
+            Duration::minutes(30),
+        );
+
+        assert!(windows.is_empty());
+
+        let windows = find_windows(
+            &people[..1],
+            &WorkingHours::default(),
+            utc(16, 12),
+            1,
+            Duration::minutes(30),
+        );
+
+        assert_eq!(windows[0].start, utc(16, 12));
+        assert_eq!(windows[0].end, utc(16, 17));
+    }
+
+    #[test]
+    fn test_format_range_across_midnight() {
+        let start = utc(16, 22).naive_utc();
+
+        assert_eq!(
+            format_range(start, start + Duration::hours(1)),
+            "Mon 22:00-23:00"
+        );
+        assert_eq!(
+            format_range(start, start + Duration::hours(3)),
+            "Mon 22:00-Tue 01:00"
+        );
+    }
+}
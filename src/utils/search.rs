@@ -0,0 +1,54 @@
+use crate::handlers::storage::Person;
+
+/// Checks if every character of the pattern appears in the text, in the same order,
+/// but not necessarily next to each other. Case is ignored.
+pub fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text_chars = text.chars().flat_map(char::to_lowercase);
+
+    pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|p| text_chars.any(|t| t == p))
+}
+
+/// Checks if the pattern matches someone's name, any of their tags, or their timezone.
+/// An empty pattern matches everyone.
+pub fn matches_person(pattern: &str, person: &Person) -> bool {
+    fuzzy_match(pattern, &person.name)
+        || person.tags.iter().any(|tag| fuzzy_match(pattern, tag))
+        || fuzzy_match(pattern, &person.timezone.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::timezones::Timezone;
+
+    #[test]
+    fn test_fuzzy_match_with_nothing() {
+        assert!(fuzzy_match("", "anything"));
+        assert!(!fuzzy_match("a", ""));
+    }
+
+    #[test]
+    fn test_fuzzy_match_subsequence() {
+        assert!(fuzzy_match("jdo", "John Doe"));
+        assert!(fuzzy_match("JOHN", "john"));
+        assert!(fuzzy_match("john doe", "JohnDoe"));
+        assert!(!fuzzy_match("odj", "John Doe"));
+    }
+
+    #[test]
+    fn test_matches_person_by_tag_and_zone() {
+        let person = Person {
+            tags: vec!["backend".to_string()],
+            ..Person::for_test("Alice", Timezone::Named(chrono_tz::America::Sao_Paulo))
+        };
+
+        assert!(matches_person("alc", &person));
+        assert!(matches_person("bkend", &person));
+        assert!(matches_person("paulo", &person));
+        assert!(!matches_person("berlin", &person));
+    }
+}
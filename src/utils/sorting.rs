@@ -0,0 +1,171 @@
+use std::{cmp::Ordering, fmt};
+
+use chrono::{DateTime, Timelike, Utc};
+
+use crate::handlers::{
+    config::{SortDirection, SortKey},
+    storage::Person,
+};
+
+/// How the rows of the table are currently ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub direction: SortDirection,
+}
+
+impl Sort {
+    pub const fn new(key: SortKey, direction: SortDirection) -> Self {
+        Self { key, direction }
+    }
+
+    /// The next sort mode in the cycle, going through every key ascending
+    /// then descending, before returning to the order people were added in.
+    pub const fn next(self) -> Self {
+        match (self.key, self.direction) {
+            (key, SortDirection::Ascending) if !matches!(key, SortKey::Added) => {
+                Self::new(key, SortDirection::Descending)
+            }
+            (SortKey::Added, _) => Self::new(SortKey::Name, SortDirection::Ascending),
+            (SortKey::Name, _) => Self::new(SortKey::Offset, SortDirection::Ascending),
+            (SortKey::Offset, _) => Self::new(SortKey::Time, SortDirection::Ascending),
+            (SortKey::Time, _) => Self::new(SortKey::Added, SortDirection::Ascending),
+        }
+    }
+
+    /// Sorts people in place. People that compare equal keep the order they were added in.
+    pub fn apply(self, people: &mut [Person], instant: DateTime<Utc>) {
+        people.sort_by(|a, b| {
+            let ordering = match self.key {
+                SortKey::Added => Ordering::Equal,
+                SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                SortKey::Offset => a
+                    .timezone
+                    .offset_seconds_at(instant)
+                    .cmp(&b.timezone.offset_seconds_at(instant)),
+                SortKey::Time => a
+                    .timezone
+                    .local_time_at(instant)
+                    .num_seconds_from_midnight()
+                    .cmp(
+                        &b.timezone
+                            .local_time_at(instant)
+                            .num_seconds_from_midnight(),
+                    ),
+            };
+
+            match self.direction {
+                SortDirection::Ascending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            }
+        });
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = match self.key {
+            SortKey::Added => return write!(f, "added"),
+            SortKey::Name => "name",
+            SortKey::Offset => "offset",
+            SortKey::Time => "time",
+        };
+
+        let arrow = match self.direction {
+            SortDirection::Ascending => '↑',
+            SortDirection::Descending => '↓',
+        };
+
+        write!(f, "{key} {arrow}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::utils::timezones::Timezone;
+
+    fn people() -> Vec<Person> {
+        [
+            ("carol", Timezone::Offset(-300)),
+            ("Alice", Timezone::Offset(60)),
+            ("bob", Timezone::Offset(600)),
+            ("Dave", Timezone::Offset(60)),
+        ]
+        .into_iter()
+        .map(|(name, timezone)| Person::for_test(name, timezone))
+        .collect()
+    }
+
+    fn names(people: &[Person]) -> Vec<&str> {
+        people.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn noon() -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(2023, 7, 15)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_sort_added_keeps_insertion_order() {
+        let mut people = people();
+
+        Sort::new(SortKey::Added, SortDirection::Descending).apply(&mut people, noon());
+
+        assert_eq!(names(&people), vec!["carol", "Alice", "bob", "Dave"]);
+    }
+
+    #[test]
+    fn test_sort_name_ignores_case() {
+        let mut people = people();
+
+        Sort::new(SortKey::Name, SortDirection::Ascending).apply(&mut people, noon());
+
+        assert_eq!(names(&people), vec!["Alice", "bob", "carol", "Dave"]);
+    }
+
+    #[test]
+    fn test_sort_offset_ties_keep_insertion_order() {
+        let mut people = people();
+
+        Sort::new(SortKey::Offset, SortDirection::Ascending).apply(&mut people, noon());
+        assert_eq!(names(&people), vec!["carol", "Alice", "Dave", "bob"]);
+
+        Sort::new(SortKey::Offset, SortDirection::Descending).apply(&mut people, noon());
+        assert_eq!(names(&people), vec!["bob", "Alice", "Dave", "carol"]);
+    }
+
+    #[test]
+    fn test_sort_time_wraps_around_midnight() {
+        let mut people = people();
+
+        // At noon UTC, bob is at 22:00, Alice and Dave at 13:00, and carol at 07:00.
+        Sort::new(SortKey::Time, SortDirection::Ascending).apply(&mut people, noon());
+        assert_eq!(names(&people), vec!["carol", "Alice", "Dave", "bob"]);
+
+        // Six hours later bob has passed midnight, so his clock is the earliest.
+        let evening = noon() + chrono::Duration::hours(6);
+        Sort::new(SortKey::Time, SortDirection::Ascending).apply(&mut people, evening);
+        assert_eq!(names(&people), vec!["bob", "carol", "Alice", "Dave"]);
+    }
+
+    #[test]
+    fn test_sort_next_cycles_through_every_mode() {
+        let start = Sort::new(SortKey::Added, SortDirection::Ascending);
+
+        let mut sort = start.next();
+        let mut steps = 1;
+
+        while sort != start {
+            sort = sort.next();
+            steps += 1;
+        }
+
+        assert_eq!(steps, 7);
+    }
+}
@@ -1,38 +1,280 @@
+use std::env;
+
 use tui::style::{Color, Modifier, Style};
 
-pub const BORDER_NAME: Style = Style {
-    fg: Some(Color::White),
-    bg: None,
-    add_modifier: Modifier::empty(),
-    sub_modifier: Modifier::empty(),
-    underline_color: None,
-};
-pub const COLUMN_TITLE: Style = Style {
-    fg: Some(Color::LightCyan),
-    bg: None,
-    add_modifier: Modifier::BOLD,
-    sub_modifier: Modifier::empty(),
-    underline_color: None,
-};
-
-pub const VALID: Style = Style {
-    fg: Some(Color::Green),
-    bg: None,
-    add_modifier: Modifier::empty(),
-    sub_modifier: Modifier::empty(),
-    underline_color: None,
-};
-pub const EXISTS: Style = Style {
-    fg: Some(Color::Yellow),
-    bg: None,
-    add_modifier: Modifier::empty(),
-    sub_modifier: Modifier::empty(),
-    underline_color: None,
-};
-pub const INVALID: Style = Style {
-    fg: Some(Color::Red),
-    bg: None,
-    add_modifier: Modifier::empty(),
-    sub_modifier: Modifier::empty(),
-    underline_color: None,
-};
+use crate::handlers::config::{StyleConfig, ThemeConfig, ThemePreset};
+
+/// Every style that's drawn, decided once when starting up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub border: Style,
+    pub column_title: Style,
+    pub selected: Style,
+    pub title: Style,
+    pub text: Style,
+    pub valid: Style,
+    pub exists: Style,
+    pub invalid: Style,
+    pub working: Style,
+    pub off_hours: Style,
+    pub sleeping: Style,
+}
+
+impl Theme {
+    pub const fn dark() -> Self {
+        Self {
+            border: Style::new().fg(Color::White),
+            column_title: Style::new()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+            selected: Style::new().add_modifier(Modifier::REVERSED),
+            title: Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+            text: Style::new().fg(Color::Yellow),
+            valid: Style::new().fg(Color::Green),
+            exists: Style::new().fg(Color::Yellow),
+            invalid: Style::new().fg(Color::Red),
+            working: Style::new().fg(Color::LightGreen),
+            off_hours: Style::new(),
+            sleeping: Style::new().fg(Color::DarkGray),
+        }
+    }
+
+    /// Darker colors that can still be read on a white background.
+    pub const fn light() -> Self {
+        Self {
+            border: Style::new().fg(Color::Black),
+            column_title: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            selected: Style::new().add_modifier(Modifier::REVERSED),
+            title: Style::new()
+                .fg(Color::Rgb(0xaf, 0x00, 0x00))
+                .add_modifier(Modifier::BOLD),
+            text: Style::new().fg(Color::Rgb(0x5f, 0x00, 0x87)),
+            valid: Style::new().fg(Color::Rgb(0x00, 0x87, 0x00)),
+            exists: Style::new().fg(Color::Rgb(0xaf, 0x5f, 0x00)),
+            invalid: Style::new().fg(Color::Rgb(0xaf, 0x00, 0x00)),
+            working: Style::new().fg(Color::Rgb(0x00, 0x5f, 0x00)),
+            off_hours: Style::new(),
+            sleeping: Style::new().fg(Color::Rgb(0x80, 0x80, 0x80)),
+        }
+    }
+
+    /// Bright colors on black, with everything in bold.
+    pub const fn high_contrast() -> Self {
+        Self {
+            border: Style::new()
+                .fg(Color::White)
+                .bg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            column_title: Style::new()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD.union(Modifier::UNDERLINED)),
+            selected: Style::new()
+                .fg(Color::Black)
+                .bg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            title: Style::new()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            text: Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+            valid: Style::new()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            exists: Style::new()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            invalid: Style::new()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            working: Style::new()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            off_hours: Style::new().fg(Color::White),
+            sleeping: Style::new().fg(Color::Gray),
+        }
+    }
+
+    /// No colors at all, only modifiers.
+    pub const fn monochrome() -> Self {
+        Self {
+            border: Style::new(),
+            column_title: Style::new().add_modifier(Modifier::BOLD),
+            selected: Style::new().add_modifier(Modifier::REVERSED),
+            title: Style::new().add_modifier(Modifier::BOLD),
+            text: Style::new(),
+            valid: Style::new(),
+            exists: Style::new().add_modifier(Modifier::BOLD),
+            invalid: Style::new().add_modifier(Modifier::BOLD.union(Modifier::ITALIC)),
+            working: Style::new().add_modifier(Modifier::BOLD),
+            off_hours: Style::new(),
+            sleeping: Style::new().add_modifier(Modifier::DIM),
+        }
+    }
+
+    /// The preset of the configuration, with its changes on top.
+    /// Colors are dropped when monochrome is turned on, or when the terminal asks for it.
+    pub fn new(config: &ThemeConfig) -> Self {
+        let monochrome = config.monochrome || no_color();
+
+        let preset = match config.preset {
+            _ if monochrome => Self::monochrome(),
+            ThemePreset::Dark => Self::dark(),
+            ThemePreset::Light => Self::light(),
+            ThemePreset::HighContrast => Self::high_contrast(),
+        };
+
+        let theme = Self {
+            border: apply(preset.border, &config.border),
+            column_title: apply(preset.column_title, &config.column_title),
+            selected: apply(preset.selected, &config.selected),
+            title: apply(preset.title, &config.title),
+            text: apply(preset.text, &config.text),
+            valid: apply(preset.valid, &config.valid),
+            exists: apply(preset.exists, &config.exists),
+            invalid: apply(preset.invalid, &config.invalid),
+            working: apply(preset.working, &config.working),
+            off_hours: apply(preset.off_hours, &config.off_hours),
+            sleeping: apply(preset.sleeping, &config.sleeping),
+        };
+
+        if monochrome {
+            theme.map(without_colors)
+        } else {
+            theme
+        }
+    }
+
+    fn map(self, f: impl Fn(Style) -> Style) -> Self {
+        Self {
+            border: f(self.border),
+            column_title: f(self.column_title),
+            selected: f(self.selected),
+            title: f(self.title),
+            text: f(self.text),
+            valid: f(self.valid),
+            exists: f(self.exists),
+            invalid: f(self.invalid),
+            working: f(self.working),
+            off_hours: f(self.off_hours),
+            sleeping: f(self.sleeping),
+        }
+    }
+}
+
+/// Whether the `NO_COLOR` environment variable is set to anything, see <https://no-color.org/>.
+fn no_color() -> bool {
+    matches!(env::var_os("NO_COLOR"), Some(value) if !value.is_empty())
+}
+
+const fn without_colors(style: Style) -> Style {
+    Style {
+        fg: None,
+        bg: None,
+        underline_color: None,
+        ..style
+    }
+}
+
+fn apply(mut style: Style, changes: &StyleConfig) -> Style {
+    if let Some(fg) = changes.fg {
+        style.fg = Some(fg.0);
+    }
+
+    if let Some(bg) = changes.bg {
+        style.bg = Some(bg.0);
+    }
+
+    if let Some(modifiers) = &changes.modifiers {
+        style.add_modifier = modifiers
+            .iter()
+            .fold(Modifier::empty(), |all, m| all | Modifier::from(*m));
+    }
+
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_is_dark_preset() {
+        assert_eq!(Theme::new(&ThemeConfig::default()), Theme::dark());
+    }
+
+    #[test]
+    fn test_changes_apply_on_top_of_preset() {
+        let config: ThemeConfig = toml::from_str(
+            r##"
+            preset = "light"
+
+            [title]
+            fg = "#ff8700"
+            modifiers = ["italic", "underlined"]
+
+            [invalid]
+            bg = "lightred"
+            "##,
+        )
+        .unwrap();
+
+        let theme = Theme::new(&config);
+
+        assert_eq!(
+            theme.title,
+            Style::new()
+                .fg(Color::Rgb(0xff, 0x87, 0x00))
+                .add_modifier(Modifier::ITALIC | Modifier::UNDERLINED)
+        );
+        assert_eq!(theme.invalid, Theme::light().invalid.bg(Color::LightRed));
+        assert_eq!(theme.border, Theme::light().border);
+    }
+
+    #[test]
+    fn test_empty_modifiers_clear_preset() {
+        let config: ThemeConfig = toml::from_str(
+            "
+            [column_title]
+            modifiers = []
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Theme::new(&config).column_title,
+            Style::new().fg(Color::LightCyan)
+        );
+    }
+
+    #[test]
+    fn test_monochrome_drops_every_color() {
+        let config: ThemeConfig = toml::from_str(
+            r##"
+            preset = "high-contrast"
+            monochrome = true
+
+            [valid]
+            fg = "#00ff00"
+            modifiers = ["underlined"]
+            "##,
+        )
+        .unwrap();
+
+        let theme = Theme::new(&config);
+
+        assert_eq!(theme.valid, Style::new().add_modifier(Modifier::UNDERLINED));
+        assert_eq!(theme.invalid, Theme::monochrome().invalid);
+    }
+
+    #[test]
+    fn test_unknown_color_is_rejected() {
+        let err = toml::from_str::<ThemeConfig>(
+            r#"
+            [text]
+            fg = "octarine"
+            "#,
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("Unknown color octarine."));
+    }
+}
@@ -6,7 +6,39 @@ use tui::{
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-use crate::handlers::config::Alignment;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    handlers::{
+        config::{Alignment, FrontendConfig},
+        storage::Person,
+    },
+    utils::availability::status_of,
+};
+
+/// The columns of the table of everyone being tracked.
+pub const TABLE_HEADERS: [&str; 5] = ["User", "Offset", "Time", "Status", "Next"];
+
+/// A single row of the table, as it looks at a specific instant.
+pub fn table_row(
+    person: &Person,
+    instant: DateTime<Utc>,
+    frontend: &FrontendConfig,
+) -> Vec<String> {
+    let status = status_of(person, instant, &frontend.working_hours);
+
+    vec![
+        person.name.clone(),
+        person.timezone.describe_at(instant),
+        person
+            .timezone
+            .local_time_at(instant)
+            .format(&frontend.time_format)
+            .to_string(),
+        status.availability.to_string(),
+        status.describe_change(),
+    ]
+}
 
 pub fn align_text(text: &str, maximum_length: u16, alignment: Alignment) -> String {
     assert!(
@@ -69,6 +101,29 @@ pub fn get_cursor_position(line_buffer: &LineBuffer) -> usize {
         .sum()
 }
 
+/// How far a time is from now, such as `+3h from now` or `-1d 2h 15m from now`.
+pub fn describe_shift(shift: Duration) -> String {
+    let minutes = shift.num_minutes().abs();
+
+    let parts = [
+        (minutes / (24 * 60), "d"),
+        (minutes / 60 % 24, "h"),
+        (minutes % 60, "m"),
+    ]
+    .iter()
+    .filter(|(amount, _)| *amount > 0)
+    .map(|(amount, unit)| format!("{amount}{unit}"))
+    .collect::<Vec<String>>();
+
+    if parts.is_empty() {
+        return "now".to_string();
+    }
+
+    let sign = if shift < Duration::zero() { '-' } else { '+' };
+
+    format!("{sign}{} from now", parts.join(" "))
+}
+
 pub fn title_spans<'a>(contents: Vec<Vec<&str>>, style: Style) -> Line<'a> {
     let mut complete = Vec::new();
 
@@ -87,6 +142,16 @@ pub fn title_spans<'a>(contents: Vec<Vec<&str>>, style: Style) -> Line<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_describe_shift() {
+        assert_eq!(describe_shift(Duration::zero()), "now");
+        assert_eq!(describe_shift(Duration::hours(3)), "+3h from now");
+        assert_eq!(
+            describe_shift(-(Duration::days(1) + Duration::minutes(135))),
+            "-1d 2h 15m from now"
+        );
+    }
+
     #[test]
     #[should_panic(expected = "Parameter of 'maximum_length' cannot be below 1.")]
     fn test_align_text_with_nothing() {
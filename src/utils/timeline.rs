@@ -0,0 +1,93 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+
+use crate::{
+    handlers::storage::Person,
+    utils::availability::{status_of, Availability, WorkingHours},
+};
+
+/// How many hours the timeline shows at most.
+pub const TIMELINE_HOURS: u16 = 24;
+/// How many of those hours come before the one being viewed.
+pub const HOURS_BEFORE: i64 = 6;
+
+/// The start of the first hour of the timeline, a few hours before the instant being viewed.
+pub fn timeline_start(instant: DateTime<Utc>) -> DateTime<Utc> {
+    instant
+        .duration_trunc(Duration::hours(1))
+        .unwrap_or(instant)
+        - Duration::hours(HOURS_BEFORE)
+}
+
+/// The hour on someone's clock and whether they're around, for each hour of the timeline.
+/// Both are taken from the middle of each hour, so that they agree for people whose offset
+/// isn't a whole number of hours.
+pub fn hour_cells(
+    person: &Person,
+    start: DateTime<Utc>,
+    hours: i64,
+    default_hours: &WorkingHours,
+) -> Vec<(String, Availability)> {
+    (0..hours)
+        .map(|i| {
+            let middle = start + Duration::hours(i) + Duration::minutes(30);
+
+            let label = person
+                .timezone
+                .local_time_at(middle)
+                .format("%H")
+                .to_string();
+
+            let status = status_of(person, middle, default_hours);
+
+            (label, status.availability)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::utils::timezones::Timezone;
+
+    #[test]
+    fn test_timeline_starts_before_the_viewed_hour() {
+        let instant = Utc.with_ymd_and_hms(2023, 10, 16, 12, 40, 10).unwrap();
+
+        assert_eq!(
+            timeline_start(instant),
+            Utc.with_ymd_and_hms(2023, 10, 16, 6, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hour_cells_follow_the_persons_clock() {
+        let person = Person::for_test("Alice", Timezone::Offset(120));
+
+        // Monday at 04:00 UTC, 06:00 for Alice.
+        let start = Utc.with_ymd_and_hms(2023, 10, 16, 4, 0, 0).unwrap();
+
+        let cells = hour_cells(&person, start, 12, &WorkingHours::default());
+
+        assert_eq!(cells[0], ("06".to_string(), Availability::Sleeping));
+        assert_eq!(cells[1], ("07".to_string(), Availability::OffHours));
+        assert_eq!(cells[3], ("09".to_string(), Availability::Working));
+        assert_eq!(cells[10], ("16".to_string(), Availability::Working));
+        assert_eq!(cells[11], ("17".to_string(), Availability::OffHours));
+    }
+
+    #[test]
+    fn test_hour_cells_agree_for_half_hour_offsets() {
+        let person = Person::for_test("Bob", Timezone::Offset(330));
+
+        // Monday at 02:00 UTC, 07:30 for Bob.
+        let start = Utc.with_ymd_and_hms(2023, 10, 16, 2, 0, 0).unwrap();
+
+        let cells = hour_cells(&person, start, 3, &WorkingHours::default());
+
+        assert_eq!(cells[0], ("08".to_string(), Availability::OffHours));
+        assert_eq!(cells[1], ("09".to_string(), Availability::Working));
+        assert_eq!(cells[2], ("10".to_string(), Availability::Working));
+    }
+}
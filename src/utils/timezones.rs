@@ -1,63 +1,326 @@
-use color_eyre::eyre::{anyhow, Context, Result};
+use std::{fmt, str::FromStr};
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone,
+    Utc, Weekday,
+};
+use chrono_tz::Tz;
+use color_eyre::eyre::{anyhow, bail, Context, Error, Result};
 use regex::{Captures, Regex};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-/// Validates if the text inputted contains some username,
-/// then a comma, then a timezone. The timezone can come with or without
-/// "UTC" at the start.
-pub fn validate_user_timezone_str(text: &str) -> Option<Captures> {
-    let re = Regex::new("^(.*),(UTC)?([-+][0-9]{1,2})$").unwrap();
+/// The furthest behind UTC that any place on earth is.
+const MIN_OFFSET_MINUTES: i64 = -12 * 60;
+/// The furthest ahead of UTC that any place on earth is.
+const MAX_OFFSET_MINUTES: i64 = 14 * 60;
+
+/// Where in the world someone is, relative to UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timezone {
+    /// A fixed amount of minutes away from UTC, which never observes daylight saving time.
+    Offset(i64),
+    /// A zone from the IANA database, such as `Europe/Berlin`.
+    Named(Tz),
+}
+
+impl Timezone {
+    /// A fixed amount of minutes away from UTC, as long as some place on earth could be at it.
+    pub fn from_offset(minutes: i64) -> Result<Self> {
+        if !(MIN_OFFSET_MINUTES..=MAX_OFFSET_MINUTES).contains(&minutes) {
+            bail!(
+                "Offset {} is out of range, it must be between -12:00 and +14:00.",
+                format_offset(minutes * 60)
+            );
+        }
+
+        Ok(Self::Offset(minutes))
+    }
+
+    /// The amount of seconds this timezone is away from UTC at a specific instant.
+    pub fn offset_seconds_at(&self, instant: DateTime<Utc>) -> i64 {
+        match self {
+            Self::Offset(minutes) => minutes * 60,
+            Self::Named(tz) => i64::from(
+                tz.offset_from_utc_datetime(&instant.naive_utc())
+                    .fix()
+                    .local_minus_utc(),
+            ),
+        }
+    }
+
+    /// The wall clock time of this timezone at a specific instant.
+    pub fn local_time_at(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        instant.naive_utc() + Duration::seconds(self.offset_seconds_at(instant))
+    }
+
+    /// The instant at which the wall clock of this timezone shows a specific time.
+    /// Times skipped over by daylight saving time don't exist, and times that happen
+    /// twice use the first of the two.
+    pub fn instant_from_local(&self, local: NaiveDateTime) -> Result<DateTime<Utc>> {
+        match self {
+            Self::Offset(minutes) => {
+                Ok(Utc.from_utc_datetime(&(local - Duration::minutes(*minutes))))
+            }
+            Self::Named(tz) => tz
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|instant| instant.with_timezone(&Utc))
+                .ok_or_else(|| anyhow!("{local} does not exist in {tz}, the clocks skip over it.")),
+        }
+    }
+
+    /// What goes into the offset column of the table. Named zones also show the
+    /// offset they currently observe.
+    pub fn describe_at(&self, instant: DateTime<Utc>) -> String {
+        match self {
+            Self::Offset(_) => self.to_string(),
+            Self::Named(_) => format!(
+                "{self} ({})",
+                format_offset(self.offset_seconds_at(instant))
+            ),
+        }
+    }
+}
+
+/// Formats an amount of seconds away from UTC as `+05:30`.
+pub fn format_offset(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offset(minutes) => write!(f, "{}", format_offset(minutes * 60)),
+            Self::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = Error;
+
+    /// Offsets can come with or without "UTC" at the start, and with or without
+    /// minutes, such as `+5`, `UTC+05:30` or `+0545`. Anything else is looked up
+    /// in the IANA database.
+    fn from_str(s: &str) -> Result<Self> {
+        let re = Regex::new("^(UTC)?([-+])([0-9]{1,2})(?::?([0-9]{2}))?$").unwrap();
+
+        if let Some(captures) = re.captures(s) {
+            let hours_string = &captures[3];
+
+            let hours = hours_string.parse::<i64>().with_context(|| {
+                format!("Unable to convert {hours_string} to a valid integer offset.")
+            })?;
+
+            let minutes = captures
+                .get(4)
+                .map_or(Ok(0), |m| m.as_str().parse::<i64>())
+                .with_context(|| format!("Unable to convert the minutes of {s} to an integer."))?;
+
+            if minutes >= 60 {
+                bail!("Minutes of offset {s} must be below 60.");
+            }
+
+            let sign = if &captures[2] == "-" { -1 } else { 1 };
+
+            return Self::from_offset(sign * (hours * 60 + minutes));
+        }
+
+        s.parse::<Tz>()
+            .map(Self::Named)
+            .map_err(|_| anyhow!("Unknown timezone {s}."))
+    }
+}
+
+impl Serialize for Timezone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    /// Older storage files kept bare integers, which were always whole hours.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Hours(i64),
+            Text(String),
+        }
 
-    if re.is_match(text) {
-        re.captures(text)
-    } else {
-        None
+        match Stored::deserialize(deserializer)? {
+            Stored::Hours(hours) => Ok(Self::Offset(hours * 60)),
+            Stored::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
     }
 }
 
-/// Parses the username and timezone offset, after validation.
-pub fn parse_user_timezone(text: &str) -> Result<(String, i64)> {
-    let captures = validate_user_timezone_str(text)
-        .ok_or_else(|| anyhow!("User/offset not formatted properly."))?;
+/// Validates if the text inputted contains some username,
+/// then a comma, then a timezone.
+pub fn validate_user_timezone_str(text: &str) -> Option<Captures<'_>> {
+    let re = Regex::new("^(.+),(.+)$").unwrap();
+
+    re.captures(text)
+}
+
+/// Parses the username and timezone, after validation.
+pub fn parse_user_timezone(text: &str) -> Result<(String, Timezone)> {
+    let captures = validate_user_timezone_str(text).ok_or_else(|| {
+        if !text.contains(',') {
+            anyhow!("Missing a comma between the name and the timezone.")
+        } else if text.starts_with(',') {
+            anyhow!("Missing a name before the comma.")
+        } else {
+            anyhow!("Missing a timezone after the comma.")
+        }
+    })?;
 
     let user = captures
         .get(1)
         .ok_or_else(|| anyhow!("Unable to retrieve username."))?
         .as_str();
 
-    let offset_string = captures
-        .get(captures.len() - 1)
-        .ok_or_else(|| anyhow!("Unable to retrieve offset"))?
-        .as_str();
+    let timezone = captures
+        .get(2)
+        .ok_or_else(|| anyhow!("Unable to retrieve timezone."))?
+        .as_str()
+        .parse::<Timezone>()?;
+
+    Ok((user.to_string(), timezone))
+}
+
+/// Reads a wall clock time such as `15:00`, `Fri 15:00`, or `2023-10-20T15:00`.
+/// Times without a date are on the day given, or on the first of that weekday from then on.
+pub fn parse_local_time(text: &str, day: NaiveDate) -> Result<NaiveDateTime> {
+    let text = text.trim();
+
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(local);
+        }
+    }
+
+    let weekday = text
+        .split_once(' ')
+        .and_then(|(weekday, time)| Some((weekday.parse::<Weekday>().ok()?, time.trim())));
+
+    let (day, time) = match weekday {
+        Some((weekday, time)) => {
+            let days_ahead =
+                (7 + weekday.num_days_from_monday() - day.weekday().num_days_from_monday()) % 7;
+
+            (day + Duration::days(i64::from(days_ahead)), time)
+        }
+        None => (day, text),
+    };
+
+    for format in ["%H:%M:%S", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(time, format) {
+            return Ok(day.and_time(time));
+        }
+    }
+
+    bail!("Unable to read the time {text}, use a format such as 15:00, Fri 15:00, or 2023-10-20T15:00.")
+}
+
+/// Somewhere that times can be converted from and to.
+pub enum Place {
+    /// The timezone of this computer.
+    Local,
+    Zone(Timezone),
+}
+
+impl Place {
+    /// Someone being tracked, `local`, or any timezone, in that order.
+    /// People are looked up by name, so that places don't need to know where they're kept.
+    pub fn resolve(text: &str, lookup: impl Fn(&str) -> Option<Timezone>) -> Result<Self> {
+        if let Some(timezone) = lookup(text) {
+            return Ok(Self::Zone(timezone));
+        }
+
+        if text.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+
+        text.parse()
+            .map(Self::Zone)
+            .wrap_err_with(|| format!("{text} is neither someone being tracked nor a timezone."))
+    }
+
+    pub fn local_time_at(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Self::Local => instant.with_timezone(&Local).naive_local(),
+            Self::Zone(timezone) => timezone.local_time_at(instant),
+        }
+    }
+
+    pub fn instant_from_local(&self, local: NaiveDateTime) -> Result<DateTime<Utc>> {
+        match self {
+            Self::Local => Local
+                .from_local_datetime(&local)
+                .earliest()
+                .map(|instant| instant.with_timezone(&Utc))
+                .ok_or_else(|| anyhow!("{local} does not exist here, the clocks skip over it.")),
+            Self::Zone(timezone) => timezone.instant_from_local(local),
+        }
+    }
+}
 
-    let offset = offset_string
-        .parse::<i64>()
-        .with_context(|| format!("Unable to convert {offset_string} to a valid integer offset."));
+/// Reads a time such as `Fri 15:00` on the local clock, or `Fri 15:00 @Alice` on someone
+/// else's. Anything after the `@` is resolved the same as a place to convert from.
+pub fn parse_conversion(
+    text: &str,
+    lookup: impl Fn(&str) -> Option<Timezone>,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>> {
+    let (time, place) = match text.rsplit_once('@') {
+        Some((time, place)) => (time, Place::resolve(place.trim(), lookup)?),
+        None => (text, Place::Local),
+    };
 
-    Ok((user.to_string(), offset.unwrap()))
+    let local = parse_local_time(time, place.local_time_at(now).date())?;
+
+    place.instant_from_local(local)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Looks up a single person, as if they were the only one being tracked.
+    fn only(name: &'static str, timezone: Timezone) -> impl Fn(&str) -> Option<Timezone> {
+        move |text| (text == name).then_some(timezone)
+    }
+
     #[test]
     fn test_parse_user_timezone_with_nothing() {
         if let Err(err) = parse_user_timezone("") {
-            assert_eq!(err.to_string(), "User/offset not formatted properly.");
+            assert_eq!(
+                err.to_string(),
+                "Missing a comma between the name and the timezone."
+            );
         }
     }
 
     #[test]
     fn test_parse_user_timezone_with_sep_no_username() {
         if let Err(err) = parse_user_timezone(",asdfUTC+8") {
-            assert_eq!(err.to_string(), "User/offset not formatted properly.");
+            assert_eq!(err.to_string(), "Missing a name before the comma.");
         }
     }
 
     #[test]
     fn test_parse_user_timezone_with_sep_no_timezone() {
         if let Err(err) = parse_user_timezone("ausername,") {
-            assert_eq!(err.to_string(), "User/offset not formatted properly.");
+            assert_eq!(err.to_string(), "Missing a timezone after the comma.");
         }
     }
 
@@ -65,7 +328,7 @@ mod tests {
     fn test_parse_user_timezone_with_valid_username_positive_timezone() {
         if let Ok((user, offset)) = parse_user_timezone("SomeName,UTC+4") {
             assert_eq!(user, "SomeName".to_string());
-            assert_eq!(offset, 4);
+            assert_eq!(offset, Timezone::Offset(240));
         }
     }
 
@@ -73,7 +336,7 @@ mod tests {
     fn test_parse_user_timezone_with_valid_username_negative_timezone() {
         if let Ok((user, offset)) = parse_user_timezone("SomeName,UTC-4") {
             assert_eq!(user, "SomeName".to_string());
-            assert_eq!(offset, -4);
+            assert_eq!(offset, Timezone::Offset(-240));
         }
     }
 
@@ -94,4 +357,239 @@ mod tests {
         assert_eq!(user0, user1);
         assert_eq!(offset0, offset1);
     }
+
+    #[test]
+    fn test_parse_user_timezone_with_named_zone() {
+        let (user, timezone) = parse_user_timezone("SomeName,Europe/Berlin").unwrap();
+
+        assert_eq!(user, "SomeName".to_string());
+        assert_eq!(timezone, Timezone::Named(chrono_tz::Europe::Berlin));
+    }
+
+    #[test]
+    fn test_parse_user_timezone_with_unknown_zone() {
+        let err = parse_user_timezone("SomeName,Europe/Atlantis").unwrap_err();
+
+        assert_eq!(err.to_string(), "Unknown timezone Europe/Atlantis.");
+    }
+
+    #[test]
+    fn test_parse_timezone_with_minutes() {
+        assert_eq!(
+            "UTC+05:30".parse::<Timezone>().unwrap(),
+            Timezone::Offset(330)
+        );
+        assert_eq!("+0545".parse::<Timezone>().unwrap(), Timezone::Offset(345));
+        assert_eq!(
+            "-03:30".parse::<Timezone>().unwrap(),
+            Timezone::Offset(-210)
+        );
+        assert_eq!("+9:30".parse::<Timezone>().unwrap(), Timezone::Offset(570));
+    }
+
+    #[test]
+    fn test_parse_timezone_with_too_many_minutes() {
+        let err = "+05:75".parse::<Timezone>().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Minutes of offset +05:75 must be below 60."
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone_out_of_range() {
+        let err = "+99".parse::<Timezone>().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Offset +99:00 is out of range, it must be between -12:00 and +14:00."
+        );
+        assert!("UTC-12:30".parse::<Timezone>().is_err());
+        assert_eq!("+14".parse::<Timezone>().unwrap(), Timezone::Offset(840));
+        assert_eq!("-12".parse::<Timezone>().unwrap(), Timezone::Offset(-720));
+    }
+
+    #[test]
+    fn test_instant_from_local() {
+        let local = NaiveDate::from_ymd_opt(2023, 7, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+
+        assert_eq!(
+            Timezone::Offset(330).instant_from_local(local).unwrap(),
+            Utc.with_ymd_and_hms(2023, 7, 1, 3, 30, 0).unwrap()
+        );
+        assert_eq!(
+            Timezone::Named(chrono_tz::Europe::Berlin)
+                .instant_from_local(local)
+                .unwrap(),
+            Utc.with_ymd_and_hms(2023, 7, 1, 7, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_instant_from_local_skipped_by_daylight_saving_time() {
+        let local = NaiveDate::from_ymd_opt(2023, 3, 26)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+
+        assert!(Timezone::Named(chrono_tz::Europe::Berlin)
+            .instant_from_local(local)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_local_time() {
+        let day = NaiveDate::from_ymd_opt(2023, 10, 20).unwrap();
+
+        assert_eq!(
+            parse_local_time("15:00", day).unwrap(),
+            day.and_hms_opt(15, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_local_time("2023-12-24T08:30", day).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 12, 24)
+                .unwrap()
+                .and_hms_opt(8, 30, 0)
+                .unwrap()
+        );
+        assert_eq!(
+            parse_local_time("2023-12-24 08:30:15", day).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 12, 24)
+                .unwrap()
+                .and_hms_opt(8, 30, 15)
+                .unwrap()
+        );
+        assert!(parse_local_time("teatime", day).is_err());
+    }
+
+    #[test]
+    fn test_parse_local_time_on_a_weekday() {
+        // The 20th of October 2023 is a Friday.
+        let day = NaiveDate::from_ymd_opt(2023, 10, 20).unwrap();
+
+        assert_eq!(
+            parse_local_time("Fri 15:00", day).unwrap(),
+            day.and_hms_opt(15, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_local_time("monday 09:30", day).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 10, 23)
+                .unwrap()
+                .and_hms_opt(9, 30, 0)
+                .unwrap()
+        );
+        assert!(parse_local_time("Fri teatime", day).is_err());
+    }
+
+    #[test]
+    fn test_format_offset() {
+        assert_eq!(format_offset(0), "+00:00");
+        assert_eq!(format_offset(19800), "+05:30");
+        assert_eq!(format_offset(-12600), "-03:30");
+        assert_eq!(format_offset(-36000), "-10:00");
+    }
+
+    #[test]
+    fn test_named_timezone_observes_daylight_saving_time() {
+        let timezone = Timezone::Named(chrono_tz::Europe::Berlin);
+
+        let winter = NaiveDate::from_ymd_opt(2023, 1, 15)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc();
+        let summer = NaiveDate::from_ymd_opt(2023, 7, 15)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc();
+
+        assert_eq!(timezone.offset_seconds_at(winter), 3600);
+        assert_eq!(timezone.offset_seconds_at(summer), 7200);
+        assert_eq!(
+            timezone.local_time_at(summer).time(),
+            summer.naive_utc().time() + Duration::hours(2)
+        );
+        assert_eq!(timezone.describe_at(summer), "Europe/Berlin (+02:00)");
+    }
+
+    #[test]
+    fn test_timezone_deserializes_legacy_offsets_and_names() {
+        let offset: Timezone = serde_json::from_str("-4").unwrap();
+        let named: Timezone = serde_json::from_str("\"America/Sao_Paulo\"").unwrap();
+
+        assert_eq!(offset, Timezone::Offset(-240));
+        assert_eq!(named, Timezone::Named(chrono_tz::America::Sao_Paulo));
+    }
+
+    #[test]
+    fn test_timezone_serializes_minute_offsets() {
+        let offset = Timezone::Offset(345);
+
+        let serialized = serde_json::to_string(&offset).unwrap();
+
+        assert_eq!(serialized, "\"+05:45\"");
+        assert_eq!(
+            serde_json::from_str::<Timezone>(&serialized).unwrap(),
+            offset
+        );
+    }
+
+    #[test]
+    fn test_place_prefers_people_over_timezones() {
+        let lookup = only("UTC", Timezone::Offset(120));
+
+        let instant = Utc.with_ymd_and_hms(2023, 10, 20, 12, 0, 0).unwrap();
+        let noon = NaiveDate::from_ymd_opt(2023, 10, 20)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+
+        let place = Place::resolve("UTC", &lookup).unwrap();
+
+        assert_eq!(place.local_time_at(instant), noon + Duration::hours(2));
+        assert!(Place::resolve("Europe/Berlin", &lookup).is_ok());
+        assert!(Place::resolve("Nobody", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_convert_between_places() {
+        let from = Place::resolve("+5:30", |_| None).unwrap();
+        let to = Place::resolve("America/New_York", |_| None).unwrap();
+
+        let local = parse_local_time("2023-10-20T18:30", NaiveDate::MIN).unwrap();
+        let instant = from.instant_from_local(local).unwrap();
+
+        assert_eq!(
+            to.local_time_at(instant).format("%F %R").to_string(),
+            "2023-10-20 09:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_conversion_in_someone_elses_zone() {
+        let lookup = only("Alice", Timezone::Offset(120));
+
+        // A Wednesday.
+        let now = Utc.with_ymd_and_hms(2023, 10, 18, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            parse_conversion("2023-10-20T09:00 @Alice", &lookup, now).unwrap(),
+            Utc.with_ymd_and_hms(2023, 10, 20, 7, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_conversion("Fri 15:00@+5:30", &lookup, now).unwrap(),
+            Utc.with_ymd_and_hms(2023, 10, 20, 9, 30, 0).unwrap()
+        );
+        assert_eq!(
+            parse_conversion("15:00 @Bob", &lookup, now)
+                .unwrap_err()
+                .to_string(),
+            "Bob is neither someone being tracked nor a timezone."
+        );
+    }
 }
@@ -0,0 +1,222 @@
+use crate::{handlers::storage::Person, utils::timezones::Timezone};
+
+/// The name and timezone of a single contact from a vCard file, as written in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contact {
+    /// The line that the contact starts on.
+    pub line: u64,
+    pub name: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// Joins lines that were folded onto the next one, keeping the line each starts on.
+fn unfold(text: &str) -> Vec<(u64, String)> {
+    let mut lines: Vec<(u64, String)> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, previous))) => previous.push_str(rest),
+            _ => lines.push((i as u64 + 1, line.to_string())),
+        }
+    }
+
+    lines
+}
+
+/// Splits a line into its name, without any group or parameters, and its value.
+fn split_property(line: &str) -> Option<(String, &str)> {
+    let mut quoted = false;
+
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+
+    let name = line[..colon].split(';').next()?;
+    let name = name.rsplit('.').next()?;
+
+    Some((name.to_uppercase(), &line[colon + 1..]))
+}
+
+/// The first component of a text value, with its escapes undone.
+fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(escaped) => text.push(escaped),
+                None => {}
+            },
+            ';' => break,
+            _ => text.push(c),
+        }
+    }
+
+    text.trim().to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+/// Reads every contact of a vCard file. Contacts without a formatted name are
+/// named after their structured name instead.
+pub fn parse_vcards(text: &str) -> Vec<Contact> {
+    let mut contacts = vec![];
+    let mut current: Option<(Contact, Option<String>)> = None;
+
+    for (line, content) in unfold(text) {
+        let Some((name, value)) = split_property(&content) else {
+            continue;
+        };
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VCARD") => {
+                let contact = Contact {
+                    line,
+                    name: None,
+                    timezone: None,
+                };
+
+                current = Some((contact, None));
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VCARD") => {
+                let (mut contact, structured_name) = current.take().unwrap();
+
+                contact.name = contact.name.or(structured_name);
+
+                contacts.push(contact);
+            }
+            ("FN", Some((contact, _))) => {
+                contact.name = Some(unescape(value)).filter(|n| !n.is_empty());
+            }
+            ("N", Some((_, structured_name))) => {
+                let mut parts = value.split(';').map(unescape);
+                let family = parts.next().unwrap_or_default();
+                let given = parts.next().unwrap_or_default();
+
+                let name = format!("{given} {family}").trim().to_string();
+
+                *structured_name = Some(name).filter(|n| !n.is_empty());
+            }
+            ("TZ", Some((contact, _))) => {
+                contact.timezone = Some(unescape(value)).filter(|t| !t.is_empty());
+            }
+            _ => {}
+        }
+    }
+
+    contacts
+}
+
+/// A vCard for everyone, with their name and timezone.
+pub fn to_vcards(people: &[Person]) -> String {
+    let mut text = String::new();
+
+    for person in people {
+        let timezone = match person.timezone {
+            Timezone::Offset(_) => format!(
+                "TZ;VALUE=utc-offset:{}",
+                person.timezone.to_string().replace(':', "")
+            ),
+            Timezone::Named(_) => format!("TZ:{}", person.timezone),
+        };
+
+        for line in [
+            "BEGIN:VCARD",
+            "VERSION:4.0",
+            &format!("FN:{}", escape(&person.name)),
+            &timezone,
+            "END:VCARD",
+        ] {
+            text.push_str(line);
+            text.push_str("\r\n");
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vcards() {
+        let text = "BEGIN:VCARD\r\n\
+                    VERSION:3.0\r\n\
+                    FN:Doe\\, Jane\r\n\
+                    item1.TZ;VALUE=text:-05:00\r\n\
+                    END:VCARD\r\n\
+                    BEGIN:VCARD\r\n\
+                    VERSION:4.0\r\n\
+                    N:Smith;John;;;\r\n\
+                    TZ:Europe/\r\n \
+                    Berlin\r\n\
+                    END:VCARD\r\n\
+                    BEGIN:VCARD\r\n\
+                    FN:Nobody\r\n\
+                    END:VCARD\r\n";
+
+        assert_eq!(
+            parse_vcards(text),
+            vec![
+                Contact {
+                    line: 1,
+                    name: Some("Doe, Jane".to_string()),
+                    timezone: Some("-05:00".to_string()),
+                },
+                Contact {
+                    line: 6,
+                    name: Some("John Smith".to_string()),
+                    timezone: Some("Europe/Berlin".to_string()),
+                },
+                Contact {
+                    line: 12,
+                    name: Some("Nobody".to_string()),
+                    timezone: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vcards_round_trip() {
+        let people = vec![
+            Person::for_test("Doe; Jane", Timezone::Offset(-210)),
+            Person::for_test("Bob", Timezone::Named(chrono_tz::Asia::Kolkata)),
+        ];
+
+        let text = to_vcards(&people);
+
+        assert!(text.contains("TZ;VALUE=utc-offset:-0330\r\n"));
+
+        let contacts = parse_vcards(&text)
+            .into_iter()
+            .map(|c| {
+                (
+                    c.name.unwrap(),
+                    c.timezone.unwrap().parse::<Timezone>().unwrap(),
+                )
+            })
+            .collect::<Vec<(String, Timezone)>>();
+
+        assert_eq!(
+            contacts,
+            people
+                .into_iter()
+                .map(|p| (p.name, p.timezone))
+                .collect::<Vec<(String, Timezone)>>()
+        );
+    }
+}